@@ -1,18 +1,24 @@
+use crate::config::Config;
 use crate::dbus::client::SolarUnneonVaxtify;
+use crate::{export, import, lint};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 #[derive(Debug)]
-struct Args {
-	permit: String,
-	is_end: bool,
+enum Args {
+	Permit { permit: String, is_end: bool, length: Option<Duration> },
+	Export { format: export::Format, categories: Vec<String> },
+	ImportLeechblock { path: String },
+	Check { path: Option<PathBuf>, lint: bool },
 }
 
-pub fn run() {
-	let argv = parse_args().unwrap();
-	let conn = dbus::blocking::Connection::new_session().unwrap();
-	let proxy = conn.with_proxy("solar.unneon.Vaxtify", "/", Duration::from_millis(500));
-	let permit = argv.permit.as_str();
-	let r = if argv.is_end { proxy.permit_end(permit) } else { proxy.permit_start(permit) };
+pub fn run(args: &[String]) {
+	let r = parse_args(args).and_then(|argv| match argv {
+		Args::Permit { permit, is_end, length } => run_permit(&permit, is_end, length),
+		Args::Export { format, categories } => run_export(format, &categories),
+		Args::ImportLeechblock { path } => run_import_leechblock(&path),
+		Args::Check { path, lint } => run_check(path.as_deref(), lint),
+	});
 	match r {
 		Ok(()) => {}
 		Err(e) => {
@@ -22,15 +28,118 @@ pub fn run() {
 	}
 }
 
-fn parse_args() -> Result<Args, &'static str> {
-	let argv = std::env::args().collect::<Vec<_>>();
+fn run_permit(permit: &str, is_end: bool, length: Option<Duration>) -> Result<(), String> {
+	let conn = dbus::blocking::Connection::new_session().unwrap();
+	let proxy = conn.with_proxy(crate::paths::bus_name(), "/", Duration::from_millis(500));
+	let seconds = length.map_or(0, |length| length.as_secs());
+	let r = if is_end { proxy.permit_end(permit) } else { proxy.permit_start(permit, seconds) };
+	r.map_err(|e| e.to_string())
+}
+
+fn run_export(format: export::Format, categories: &[String]) -> Result<(), String> {
+	let config = Config::load().map_err(|e| e.to_string())?;
+	let export = export::export(&config, format, categories)?;
+	for warning in &export.warnings {
+		eprintln!("\x1B[1;33mwarning:\x1B[0m {}", warning);
+	}
+	for line in &export.lines {
+		println!("{}", line);
+	}
+	Ok(())
+}
+
+fn run_import_leechblock(path: &str) -> Result<(), String> {
+	let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read {:?} ({})", path, e))?;
+	let import = import::import_leechblock(&text)?;
+	for warning in &import.warnings {
+		eprintln!("\x1B[1;33mwarning:\x1B[0m {}", warning);
+	}
+	print!("{}", import.config);
+	Ok(())
+}
+
+/// Loads the config without connecting to the daemon, optionally also looking for likely mistakes.
+fn run_check(path: Option<&Path>, lint: bool) -> Result<(), String> {
+	let config = match path {
+		Some(path) => Config::load_file(path),
+		None => Config::load(),
+	};
+	let config = config.map_err(|e| e.to_string())?;
+	if lint {
+		let warnings = lint::lint(&config);
+		for warning in &warnings {
+			eprintln!("\x1B[1;33mwarning:\x1B[0m {}", warning);
+		}
+		if !warnings.is_empty() {
+			std::process::exit(1);
+		}
+	}
+	Ok(())
+}
+
+fn parse_args(argv: &[String]) -> Result<Args, String> {
 	let argv = argv.iter().map(String::as_str).collect::<Vec<_>>();
-	let (permit, is_end) = match argv.as_slice() {
-		[_, "permit", permit, "end"] => (permit, true),
-		[_, "permit", permit] => (permit, false),
-		_ => return Err("arguments don't match the pattern"),
+	let args = match argv.as_slice() {
+		[_, "permit", permit, "end"] => Args::Permit { permit: (*permit).to_owned(), is_end: true, length: None },
+		[_, "permit", permit, length] => Args::Permit {
+			permit: (*permit).to_owned(),
+			is_end: false,
+			length: Some(parse_duration(length).ok_or("invalid permit length, use something like 10m or 1h30m")?),
+		},
+		[_, "permit", permit] => Args::Permit { permit: (*permit).to_owned(), is_end: false, length: None },
+		[_, "export", format, categories @ ..] if !categories.is_empty() => Args::Export {
+			format: format.parse()?,
+			categories: categories.iter().map(|category| (*category).to_owned()).collect(),
+		},
+		[_, "import", "leechblock", path] => Args::ImportLeechblock { path: (*path).to_owned() },
+		[_, "import", "coldturkey", _] => return Err("importing Cold Turkey exports is not supported yet".to_owned()),
+		[_, "check"] => Args::Check { path: None, lint: false },
+		[_, "check", path] => Args::Check { path: Some(PathBuf::from(path)), lint: false },
+		[_, "lint"] => Args::Check { path: None, lint: true },
+		[_, "lint", path] => Args::Check { path: Some(PathBuf::from(path)), lint: true },
+		_ => return Err("arguments don't match the pattern".to_owned()),
 	};
-	let permit = (*permit).to_owned();
-	let args = Args { permit, is_end };
 	Ok(args)
 }
+
+/// Parses durations like `10m` or `1h30m`, with `d`, `h`, `m` and `s` units.
+fn parse_duration(text: &str) -> Option<Duration> {
+	let mut seconds = 0u64;
+	let mut rest = text;
+	while !rest.is_empty() {
+		let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+		let value: u64 = rest[..digits].parse().ok()?;
+		let mut chars = rest[digits..].chars();
+		let unit = match chars.next()? {
+			'd' => 24 * 60 * 60,
+			'h' => 60 * 60,
+			'm' => 60,
+			's' => 1,
+			_ => return None,
+		};
+		seconds = seconds.checked_add(value.checked_mul(unit)?)?;
+		rest = chars.as_str();
+	}
+	Some(Duration::from_secs(seconds)).filter(|duration| !duration.is_zero())
+}
+
+#[test]
+fn durations() {
+	assert_eq!(parse_duration("10m"), Some(Duration::from_secs(10 * 60)));
+	assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(90 * 60)));
+	assert_eq!(parse_duration("45s"), Some(Duration::from_secs(45)));
+	assert_eq!(parse_duration("10"), None);
+	assert_eq!(parse_duration("0m"), None);
+	assert_eq!(parse_duration("m"), None);
+	assert_eq!(parse_duration(""), None);
+}
+
+#[test]
+fn invalid_arguments() {
+	let args = |args: &[&str]| args.iter().map(|arg| (*arg).to_owned()).collect::<Vec<_>>();
+	assert_eq!(parse_args(&args(&["vaxtify", "export", "pdf", "memes"])).unwrap_err(), "invalid export format \"pdf\"");
+	assert_eq!(
+		parse_args(&args(&["vaxtify", "permit", "memes", "10x"])).unwrap_err(),
+		"invalid permit length, use something like 10m or 1h30m"
+	);
+}
@@ -1,13 +1,30 @@
+mod blocklist;
+mod diagnostics;
+mod holidays;
+mod kdl_date;
 mod kdl_duration;
 mod kdl_time;
+mod kdl_url;
+mod kdl_weekday;
+mod sites;
 
-use chrono::{DateTime, Local, NaiveTime};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, Weekday};
+use diagnostics::{suggest, Block, Invalid, LineError, Source};
+use knuffel::span::Span;
+use knuffel::traits::DecodeChildren;
 use knuffel::Decode;
-use std::collections::HashSet;
-#[cfg(test)]
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+pub use blocklist::DomainsFiles;
+pub use diagnostics::Diagnostic;
+pub use kdl_url::{PathPrefix, QueryParam};
+pub use sites::Sites;
+
 #[derive(Debug, Decode)]
+#[knuffel(span_type = Span)]
 pub struct Config {
 	#[knuffel(child)]
 	pub prevent_browser_close: bool,
@@ -16,111 +33,365 @@ pub struct Config {
 	#[knuffel(child)]
 	pub close_all_after_block: Option<kdl_duration::Duration>,
 	#[knuffel(child)]
+	pub permit_cooldown_after_restart: Option<kdl_duration::Duration>,
+	#[knuffel(child)]
 	pub reload_delay: Option<kdl_duration::Duration>,
 	#[knuffel(child, default = crate::processes::DEFAULT_SCAN_EACH.into())]
 	pub processes_scan_each: kdl_duration::Duration,
+	#[knuffel(children(name = "include"), unwrap(argument))]
+	includes: Vec<String>,
+	#[knuffel(children(name = "when"))]
+	conditionals: Vec<When>,
 	#[knuffel(children(name = "category"))]
 	pub categories: Vec<Category>,
+	#[knuffel(children(name = "schedule"))]
+	pub schedules: Vec<Schedule>,
 	#[knuffel(children(name = "rule"))]
 	pub rules: Vec<Rule>,
 	#[knuffel(children(name = "permit"))]
 	pub permits: Vec<Permit>,
+	/// Files the config was read from, including holiday and domains files, which the daemon watches for changes.
+	pub files: Vec<PathBuf>,
+	/// Directory with drop-in config files, where adding or removing a file also changes the config.
+	pub drop_in_dir: Option<PathBuf>,
 }
 
+/// Config file added with an `include` node or placed in the drop-in directory, which can only contain blocks.
 #[derive(Debug, Decode)]
+#[knuffel(span_type = Span)]
+struct Fragment {
+	#[knuffel(children(name = "include"), unwrap(argument))]
+	includes: Vec<String>,
+	#[knuffel(children(name = "when"))]
+	conditionals: Vec<When>,
+	#[knuffel(children(name = "category"))]
+	categories: Vec<Category>,
+	#[knuffel(children(name = "schedule"))]
+	schedules: Vec<Schedule>,
+	#[knuffel(children(name = "rule"))]
+	rules: Vec<Rule>,
+	#[knuffel(children(name = "permit"))]
+	permits: Vec<Permit>,
+}
+
+/// Blocks that only apply on machines matching all of the given conditions, like `when hostname="laptop" { ... }`.
+#[derive(Debug, Decode)]
+#[knuffel(span_type = Span)]
+struct When {
+	#[knuffel(property)]
+	hostname: Option<String>,
+	#[knuffel(property)]
+	user: Option<String>,
+	/// Either `NAME`, which requires the environment variable to be set, or `NAME=value`.
+	#[knuffel(property)]
+	env: Option<String>,
+	#[knuffel(children(name = "include"), unwrap(argument))]
+	includes: Vec<String>,
+	#[knuffel(children(name = "category"))]
+	categories: Vec<Category>,
+	#[knuffel(children(name = "schedule"))]
+	schedules: Vec<Schedule>,
+	#[knuffel(children(name = "rule"))]
+	rules: Vec<Rule>,
+	#[knuffel(children(name = "permit"))]
+	permits: Vec<Permit>,
+}
+
+#[derive(Debug, Decode)]
+#[knuffel(span_type = Span)]
 pub struct Category {
+	#[knuffel(span)]
+	pub span: Span,
+	pub source: usize,
 	#[knuffel(argument)]
 	pub name: String,
+	#[knuffel(flatten(child))]
+	pub matchers: Matchers,
 	#[knuffel(child, unwrap(arguments))]
-	pub domains: Option<Vec<String>>,
+	pub processes: Option<Vec<String>>,
 	#[knuffel(child, unwrap(arguments))]
-	pub subreddits: Option<Vec<String>>,
+	pub includes: Option<Vec<String>>,
+	#[knuffel(child)]
+	pub except: Option<Matchers>,
+}
+
+#[derive(Debug, Decode, Default)]
+pub struct Matchers {
 	#[knuffel(child, unwrap(arguments))]
-	pub githubs: Option<Vec<String>>,
+	pub domains: Option<Vec<String>>,
 	#[knuffel(child, unwrap(arguments))]
 	pub regexes: Option<Vec<String>>,
+	#[knuffel(child, unwrap(arguments, str))]
+	pub path_prefixes: Option<Vec<kdl_url::PathPrefix>>,
+	#[knuffel(child, unwrap(arguments, str))]
+	pub query_params: Option<Vec<kdl_url::QueryParam>>,
 	#[knuffel(child, unwrap(arguments))]
-	pub processes: Option<Vec<String>>,
+	pub search_keywords: Option<Vec<String>>,
+	#[knuffel(flatten(child))]
+	pub sites: Sites,
+	#[knuffel(flatten(child))]
+	pub domains_files: DomainsFiles,
 }
 
 #[derive(Clone, Copy, Debug, Decode, Eq, PartialEq)]
 pub struct TimeRange {
-	#[knuffel(child)]
+	#[knuffel(child, default = kdl_time::NaiveTime::MIDNIGHT)]
 	pub since: kdl_time::NaiveTime,
+	/// Missing means the end of the day, so that a range with neither bound spans the whole day.
 	#[knuffel(child)]
-	pub until: kdl_time::NaiveTime,
+	pub until: Option<kdl_time::NaiveTime>,
+	#[knuffel(child, unwrap(arguments, str), default = kdl_weekday::Weekdays::ALL)]
+	pub days: kdl_weekday::Weekdays,
 }
 
 #[derive(Debug, Decode)]
+#[knuffel(span_type = Span)]
+pub struct Schedule {
+	#[knuffel(span)]
+	pub span: Span,
+	pub source: usize,
+	#[knuffel(argument)]
+	pub name: String,
+	#[knuffel(children(name = "window"))]
+	pub windows: Vec<TimeRange>,
+}
+
+#[derive(Debug, Decode)]
+#[knuffel(span_type = Span)]
 pub struct Rule {
+	#[knuffel(span)]
+	pub span: Span,
+	pub source: usize,
 	#[knuffel(argument)]
 	pub name: String,
-	#[knuffel(child)]
-	pub allowed: Option<TimeRange>,
+	#[knuffel(children(name = "allowed"))]
+	pub allowed: Vec<TimeRange>,
+	#[knuffel(child, unwrap(arguments), default)]
+	pub allowed_schedules: Vec<String>,
+	#[knuffel(children(name = "exception"))]
+	pub exceptions: Vec<Exception>,
 	#[knuffel(child, unwrap(arguments))]
 	pub categories: Vec<String>,
 }
 
 #[derive(Debug, Decode)]
+pub struct Exception {
+	#[knuffel(child, unwrap(arguments, str), default)]
+	pub dates: Vec<kdl_date::DateRange>,
+	#[knuffel(child, unwrap(arguments), default)]
+	pub holidays: Vec<String>,
+	#[knuffel(child, unwrap(argument, str))]
+	pub like: Option<kdl_weekday::Weekday>,
+	pub holiday_dates: Vec<kdl_date::DateRange>,
+}
+
+#[derive(Debug, Decode)]
+#[knuffel(span_type = Span)]
 pub struct Permit {
+	#[knuffel(span)]
+	pub span: Span,
+	pub source: usize,
 	#[knuffel(argument)]
 	pub name: String,
 	#[knuffel(child)]
-	pub length: kdl_duration::Duration,
+	pub length: Option<kdl_duration::Duration>,
 	#[knuffel(child)]
-	pub cooldown: Option<kdl_duration::Duration>,
+	pub max_length: Option<kdl_duration::Duration>,
 	#[knuffel(child)]
-	pub available: Option<TimeRange>,
+	pub cooldown: Option<kdl_duration::Duration>,
+	#[knuffel(children(name = "available"))]
+	pub available: Vec<TimeRange>,
+	#[knuffel(child, unwrap(arguments), default)]
+	pub available_schedules: Vec<String>,
 	#[knuffel(child, unwrap(arguments))]
 	pub categories: Vec<String>,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
-	#[error("config parse error")]
-	ParseError(#[from] knuffel::Error),
-	#[error("config validation failed ({details})")]
-	ValidationFailure { details: &'static str },
+	#[error("config parse error ({})", diagnostics::list(.diagnostics))]
+	ParseError { diagnostics: Vec<Diagnostic> },
+	#[error("config validation failed ({diagnostic})")]
+	ValidationFailure { details: &'static str, diagnostic: Diagnostic },
+	#[error("failed to read {path:?} ({error})")]
+	ReadError { path: PathBuf, error: std::io::Error },
+	#[error("invalid {kind} file {path:?} on line {line} ({details})")]
+	LineError { kind: &'static str, path: PathBuf, line: usize, details: String },
 }
 
-const CONFIG_FILE_NAME: &str = "vaxtify.kdl";
+pub const CONFIG_FILE_NAME: &str = "vaxtify.kdl";
 
 impl Config {
+	/// Loads the config from the default location, or the one given with `--config` or `VAXTIFY_CONFIG`.
 	pub fn load() -> Result<Config, ConfigError> {
-		let path = dirs::config_dir().unwrap().join(CONFIG_FILE_NAME);
-		let file = std::fs::read_to_string(path).unwrap();
-		Config::parse(&file)
+		Config::load_file(crate::paths::config_file())
+	}
+
+	/// Loads the config from the given file, merged with the files it includes and drop-ins from the directory next to
+	/// it, like `vaxtify.d/*.kdl` for `vaxtify.kdl`. Relative paths are resolved against the directory of the file they
+	/// appear in.
+	pub fn load_file(path: &Path) -> Result<Config, ConfigError> {
+		let mut loader = Loader::default();
+		let mut config: Config = loader.read(path)?.unwrap();
+		let fragment = config.take_fragment();
+		loader.merge(&mut config, fragment, path)?;
+		let drop_in_dir = path.with_extension("d");
+		for path in drop_ins(&drop_in_dir)? {
+			if let Some(fragment) = loader.read(&path)? {
+				loader.merge(&mut config, fragment, &path)?;
+			}
+		}
+		config.files = loader.files;
+		config.drop_in_dir = Some(drop_in_dir.canonicalize().unwrap_or(drop_in_dir));
+		config.finish(&loader.sources)
 	}
 
+	/// Parses a config from a single file, without reading included, holiday or domains files.
+	#[cfg(test)]
 	pub fn parse(file: &str) -> Result<Config, ConfigError> {
-		let config: Config = knuffel::parse(CONFIG_FILE_NAME, file)?;
-		if config.prevent_browser_close && config.close_all_after_block.is_some() {
-			return Err(ConfigError::ValidationFailure {
-				details: "prevent-browser-close and close-all-after-block can't both be set",
-			});
-		}
-		if config.close_all_on_block && config.close_all_after_block.is_some() {
-			return Err(ConfigError::ValidationFailure {
-				details: "close-all-on-block and close-all-after-block can't both be set",
-			});
-		}
-		check_unique_names(&config.categories, |c| &c.name)?;
-		check_unique_names(&config.rules, |r| &r.name)?;
-		check_unique_names(&config.permits, |p| &p.name)?;
-		config.check_categories_exist(&config.rules, |r| &r.categories)?;
-		config.check_categories_exist(&config.permits, |p| &p.categories)?;
-		Ok(config)
-	}
-
-	fn check_categories_exist<T>(
+		let source = Source { name: CONFIG_FILE_NAME.to_owned(), text: file.to_owned() };
+		let mut config: Config = decode(&source)?;
+		let mut fragment = config.take_fragment();
+		fragment.resolve_conditionals();
+		config.add_blocks(fragment);
+		config.finish(&[source])
+	}
+
+	/// Moves blocks out of the config, so that blocks from the main file are merged like blocks from other files.
+	fn take_fragment(&mut self) -> Fragment {
+		Fragment {
+			includes: std::mem::take(&mut self.includes),
+			conditionals: std::mem::take(&mut self.conditionals),
+			categories: std::mem::take(&mut self.categories),
+			schedules: std::mem::take(&mut self.schedules),
+			rules: std::mem::take(&mut self.rules),
+			permits: std::mem::take(&mut self.permits),
+		}
+	}
+
+	fn add_blocks(&mut self, fragment: Fragment) {
+		self.categories.extend(fragment.categories);
+		self.schedules.extend(fragment.schedules);
+		self.rules.extend(fragment.rules);
+		self.permits.extend(fragment.permits);
+	}
+
+	fn finish(mut self, sources: &[Source]) -> Result<Config, ConfigError> {
+		self.validate().map_err(|invalid| ConfigError::ValidationFailure {
+			details: invalid.details,
+			diagnostic: invalid.locate(sources),
+		})?;
+		self.resolve_schedules();
+		self.normalize_site_keys();
+		Ok(self)
+	}
+
+	fn validate(&mut self) -> Result<(), Invalid> {
+		if self.prevent_browser_close && self.close_all_after_block.is_some() {
+			let details = "prevent-browser-close and close-all-after-block can't both be set";
+			return Err(Invalid::new(details, details.to_owned()).help("remove one of them"));
+		}
+		if self.close_all_on_block && self.close_all_after_block.is_some() {
+			let details = "close-all-on-block and close-all-after-block can't both be set";
+			return Err(Invalid::new(details, details.to_owned()).help("remove one of them"));
+		}
+		check_unique_names(&self.categories)?;
+		check_unique_names(&self.schedules)?;
+		check_unique_names(&self.rules)?;
+		check_unique_names(&self.permits)?;
+		self.check_categories_exist(&self.categories, |c| c.includes.as_deref().unwrap_or_default())?;
+		check_includes_acyclic(&self.categories)?;
+		self.check_categories_exist(&self.rules, |r| &r.categories)?;
+		self.check_categories_exist(&self.permits, |p| &p.categories)?;
+		self.check_schedules_exist(&self.rules, |r| &r.allowed_schedules)?;
+		self.check_schedules_exist(&self.permits, |p| &p.available_schedules)?;
+		check_schedules_not_empty(&self.schedules)?;
+		check_days_not_empty(&self.schedules, |s| &s.windows)?;
+		check_days_not_empty(&self.rules, |r| &r.allowed)?;
+		check_days_not_empty(&self.permits, |p| &p.available)?;
+		check_regexes_valid(&self.categories)?;
+		check_exceptions_not_empty(&self.rules)?;
+		check_permit_lengths(&self.permits)?;
+		self.normalize_domains()
+	}
+
+	/// Normalizes domains in category matchers, so they can be compared with normalized URL hosts.
+	fn normalize_domains(&mut self) -> Result<(), Invalid> {
+		for category in &mut self.categories {
+			for matchers in std::iter::once(&mut category.matchers).chain(&mut category.except) {
+				for domain in matchers.domains.iter_mut().flatten() {
+					*domain = match normalize_domain_pattern(domain) {
+						Some(normalized) => normalized,
+						None => {
+							let message = format!("has invalid domain {:?}", domain);
+							let help =
+								"domains can start with \"*.\" or \".\" to match subdomains, but can't contain paths";
+							return Err(Invalid::in_block(&*category, "invalid domain", message).help(help));
+						}
+					};
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Normalizes site keys in category matchers, so they can be compared with keys extracted from URLs.
+	fn normalize_site_keys(&mut self) {
+		for category in &mut self.categories {
+			for matchers in std::iter::once(&mut category.matchers).chain(&mut category.except) {
+				matchers.sites.normalize();
+			}
+		}
+	}
+
+	/// Copies windows of referenced schedules into the rules and permits, so they can be evaluated on their own.
+	fn resolve_schedules(&mut self) {
+		let schedules = &self.schedules;
+		let windows = |names: &[String]| -> Vec<TimeRange> {
+			names
+				.iter()
+				.flat_map(|name| &schedules.iter().find(|s| s.name == *name).unwrap().windows)
+				.copied()
+				.collect()
+		};
+		for rule in &mut self.rules {
+			rule.allowed.extend(windows(&rule.allowed_schedules));
+		}
+		for permit in &mut self.permits {
+			permit.available.extend(windows(&permit.available_schedules));
+		}
+	}
+
+	fn check_categories_exist<T: Block>(
 		&self,
 		blocks: &[T],
 		block_categories: impl Fn(&T) -> &[String],
-	) -> Result<(), ConfigError> {
+	) -> Result<(), Invalid> {
 		for block in blocks {
 			for category in block_categories(block) {
 				if !self.categories.iter().any(|c| c.name == *category) {
-					return Err(ConfigError::ValidationFailure { details: "category does not exist" });
+					let message = format!("refers to category {:?}, which does not exist", category);
+					let help = suggest(category, self.categories.iter().map(|c| c.name.as_str()))
+						.unwrap_or_else(|| format!("define it with `category {:?} {{ ... }}`", category));
+					return Err(Invalid::in_block(block, "category does not exist", message).help(help));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	fn check_schedules_exist<T: Block>(
+		&self,
+		blocks: &[T],
+		block_schedules: impl Fn(&T) -> &[String],
+	) -> Result<(), Invalid> {
+		for block in blocks {
+			for schedule in block_schedules(block) {
+				if !self.schedules.iter().any(|s| s.name == *schedule) {
+					let message = format!("refers to schedule {:?}, which does not exist", schedule);
+					let help = suggest(schedule, self.schedules.iter().map(|s| s.name.as_str()))
+						.unwrap_or_else(|| format!("define it with `schedule {:?} {{ ... }}`", schedule));
+					return Err(Invalid::in_block(block, "schedule does not exist", message).help(help));
 				}
 			}
 		}
@@ -129,54 +400,387 @@ impl Config {
 }
 
 impl TimeRange {
-	fn contains(&self, now: &DateTime<Local>) -> bool {
+	fn contains(&self, time: NaiveTime, today: Weekday, yesterday: Weekday) -> bool {
 		let since: NaiveTime = self.since.into();
-		let until: NaiveTime = self.until.into();
-		let time = now.naive_local().time();
-		if since <= until {
-			time >= since && time < until
-		} else {
-			time >= since || time < until
+		match self.until.map(NaiveTime::from) {
+			Some(until) if since <= until => self.days.contains(today) && time >= since && time < until,
+			Some(until) => {
+				// Ranges crossing midnight belong to the day they started on.
+				(self.days.contains(today) && time >= since) || (self.days.contains(yesterday) && time < until)
+			}
+			None => self.days.contains(today) && time >= since,
+		}
+	}
+
+	fn next_change_time(&self, now: &DateTime<Local>, days: kdl_weekday::Weekdays) -> Option<DateTime<Local>> {
+		let since: NaiveTime = self.since.into();
+		let (until, until_days) = match self.until.map(NaiveTime::from) {
+			Some(until) if since <= until => (until, days),
+			until => (until.unwrap_or_else(|| NaiveTime::from_hms(0, 0, 0)), days.succ()),
+		};
+		let next_start = upper_bound_with_time(now, &since, days)?;
+		let next_end = upper_bound_with_time(now, &until, until_days)?;
+		Some(next_start.min(next_end))
+	}
+}
+
+impl fmt::Display for TimeRange {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "since {}", NaiveTime::from(self.since))?;
+		match self.until {
+			Some(until) => write!(f, " until {}", NaiveTime::from(until))?,
+			None => write!(f, " until the end of the day")?,
+		}
+		if self.days != kdl_weekday::Weekdays::ALL {
+			write!(f, " on {}", self.days)?;
 		}
+		Ok(())
 	}
 }
 
 impl Rule {
 	pub fn is_active(&self, now: &DateTime<Local>) -> bool {
-		self.allowed.map_or(true, |allowed| !allowed.contains(now))
+		let date = now.naive_local().date();
+		if self.exception_on(date).is_some_and(|exception| exception.like.is_none()) {
+			return false;
+		}
+		let time = now.naive_local().time();
+		let today = self.weekday_on(date);
+		let yesterday = self.weekday_on(date.pred());
+		!self.allowed.iter().any(|allowed| allowed.contains(time, today, yesterday))
 	}
 
 	pub fn next_change_time(&self, now: &DateTime<Local>) -> Option<DateTime<Local>> {
-		let TimeRange { since, until } = self.allowed?;
-		let next_start = upper_bound_with_time(now, &since.into());
-		let next_end = upper_bound_with_time(now, &until.into());
-		Some(next_start.min(next_end))
+		if self.exceptions.is_empty() {
+			self.allowed.iter().filter_map(|allowed| allowed.next_change_time(now, allowed.days)).min()
+		} else {
+			// Exceptions can change the rule at any midnight, and can make any window apply regardless of its days.
+			let next_midnight = upper_bound_with_time(now, &NaiveTime::from_hms(0, 0, 0), kdl_weekday::Weekdays::ALL);
+			let all_days = kdl_weekday::Weekdays::ALL;
+			self.allowed.iter().filter_map(|allowed| allowed.next_change_time(now, all_days)).chain(next_midnight).min()
+		}
+	}
+
+	fn exception_on(&self, date: NaiveDate) -> Option<&Exception> {
+		self.exceptions.iter().find(|exception| exception.contains(date))
+	}
+
+	fn weekday_on(&self, date: NaiveDate) -> Weekday {
+		match self.exception_on(date).and_then(|exception| exception.like) {
+			Some(like) => like.into(),
+			None => date.weekday(),
+		}
+	}
+}
+
+impl Exception {
+	fn contains(&self, date: NaiveDate) -> bool {
+		self.dates.iter().chain(&self.holiday_dates).any(|range| range.contains(date))
 	}
 }
 
 impl Permit {
 	pub fn is_available(&self, now: &DateTime<Local>) -> bool {
-		self.available.map_or(true, |available| available.contains(now))
+		let time = now.naive_local().time();
+		let today = now.weekday();
+		self.available.is_empty()
+			|| self.available.iter().any(|available| available.contains(time, today, today.pred()))
 	}
 }
 
-fn upper_bound_with_time(greater_than: &DateTime<Local>, set_time: &NaiveTime) -> DateTime<Local> {
+fn normalize_domain_pattern(pattern: &str) -> Option<String> {
+	let (prefix, domain) = if let Some(domain) = pattern.strip_prefix("*.") {
+		("*.", domain)
+	} else if let Some(domain) = pattern.strip_prefix('.') {
+		(".", domain)
+	} else {
+		("", pattern)
+	};
+	Some(format!("{}{}", prefix, crate::filters::normalize_domain(domain)?))
+}
+
+fn upper_bound_with_time(
+	greater_than: &DateTime<Local>,
+	set_time: &NaiveTime,
+	days: kdl_weekday::Weekdays,
+) -> Option<DateTime<Local>> {
 	let mut candidate = greater_than.date();
-	while candidate.and_time(*set_time).unwrap() <= *greater_than {
+	for _ in 0..=7 {
+		if days.contains(candidate.weekday()) && candidate.and_time(*set_time).unwrap() > *greater_than {
+			return Some(candidate.and_time(*set_time).unwrap());
+		}
 		candidate = candidate.succ();
 	}
-	candidate.and_time(*set_time).unwrap()
+	None
+}
+
+fn check_schedules_not_empty(schedules: &[Schedule]) -> Result<(), Invalid> {
+	for schedule in schedules {
+		if schedule.windows.is_empty() {
+			let details = "schedule must have at least one window";
+			let help = "add a `window { since hour=9; until hour=17; }` block";
+			return Err(Invalid::in_block(schedule, details, "has no windows").help(help));
+		}
+	}
+	Ok(())
+}
+
+fn check_days_not_empty<T: Block>(blocks: &[T], block_ranges: impl Fn(&T) -> &[TimeRange]) -> Result<(), Invalid> {
+	for block in blocks {
+		if block_ranges(block).iter().any(|range| range.days.is_empty()) {
+			let details = "days must list at least one day";
+			let help = "list days like `days \"mon-fri\" \"sun\"`, or remove the node to include every day";
+			return Err(Invalid::in_block(block, details, "has a days node without any days").help(help));
+		}
+	}
+	Ok(())
+}
+
+fn check_regexes_valid(categories: &[Category]) -> Result<(), Invalid> {
+	for category in categories {
+		for matchers in std::iter::once(&category.matchers).chain(&category.except) {
+			for regex in matchers.regexes.iter().flatten() {
+				if let Err(e) = regex::Regex::new(regex) {
+					let message = format!("has invalid regex {:?}", regex);
+					let help = e.to_string().lines().last().unwrap_or_default().to_owned();
+					return Err(Invalid::in_block(category, "invalid regex", message).help(help));
+				}
+			}
+		}
+	}
+	Ok(())
+}
+
+fn check_exceptions_not_empty(rules: &[Rule]) -> Result<(), Invalid> {
+	for rule in rules {
+		if rule.exceptions.iter().any(|exception| exception.dates.is_empty() && exception.holidays.is_empty()) {
+			let details = "exception must list dates or holidays";
+			let help = "add `dates \"2026-12-24..2026-12-26\"` or `holidays \"holidays.ics\"` to the exception";
+			return Err(Invalid::in_block(rule, details, "has an exception without dates or holidays").help(help));
+		}
+	}
+	Ok(())
+}
+
+fn check_permit_lengths(permits: &[Permit]) -> Result<(), Invalid> {
+	for permit in permits {
+		match (permit.length, permit.max_length) {
+			(None, None) => {
+				let help = "add `length mins=15`, `max-length mins=30` or both";
+				return Err(
+					Invalid::in_block(permit, "permit must have a length", "has no length or max-length").help(help)
+				);
+			}
+			(Some(length), Some(max_length)) if Duration::from(length) > Duration::from(max_length) => {
+				let details = "permit length can't be longer than its max-length";
+				return Err(Invalid::in_block(permit, details, "has a length longer than its max-length"));
+			}
+			_ => {}
+		}
+	}
+	Ok(())
+}
+
+fn check_includes_acyclic(categories: &[Category]) -> Result<(), Invalid> {
+	#[derive(Clone, Copy, PartialEq)]
+	enum Visit {
+		New,
+		InProgress,
+		Done,
+	}
+	fn visit(index: usize, categories: &[Category], state: &mut [Visit]) -> Result<(), Invalid> {
+		match state[index] {
+			Visit::Done => return Ok(()),
+			Visit::InProgress => {
+				let details = "categories can't include themselves";
+				let message = "includes itself through other categories";
+				let help = "remove one of the includes forming the cycle";
+				return Err(Invalid::in_block(&categories[index], details, message).help(help));
+			}
+			Visit::New => {}
+		}
+		state[index] = Visit::InProgress;
+		for include in categories[index].includes.iter().flatten() {
+			let include_index = categories.iter().position(|c| c.name == *include).unwrap();
+			visit(include_index, categories, state)?;
+		}
+		state[index] = Visit::Done;
+		Ok(())
+	}
+	let mut state = vec![Visit::New; categories.len()];
+	for index in 0..categories.len() {
+		visit(index, categories, &mut state)?;
+	}
+	Ok(())
+}
+
+/// Keeps track of files read while loading a config, so that each of them is merged only once.
+#[derive(Default)]
+struct Loader {
+	sources: Vec<Source>,
+	loaded: HashSet<PathBuf>,
+	files: Vec<PathBuf>,
+}
+
+impl Loader {
+	/// Reads and decodes the given file, unless it was already loaded.
+	fn read<T: DecodeChildren<Span>>(&mut self, path: &Path) -> Result<Option<T>, ConfigError> {
+		let read_error = |error| ConfigError::ReadError { path: path.to_owned(), error };
+		if !self.loaded.insert(path.canonicalize().map_err(read_error)?) {
+			return Ok(None);
+		}
+		let text = read_file(path, &mut self.files)?;
+		let source = Source { name: path.display().to_string(), text };
+		let value = decode(&source)?;
+		self.sources.push(source);
+		Ok(Some(value))
+	}
+
+	/// Adds blocks from the file read most recently to the config, followed by blocks from the files it includes.
+	fn merge(&mut self, config: &mut Config, mut fragment: Fragment, path: &Path) -> Result<(), ConfigError> {
+		let dir = path.parent().unwrap_or_else(|| Path::new("."));
+		fragment.resolve_conditionals();
+		fragment.prepare(self.sources.len() - 1, dir, &mut self.files)?;
+		let includes = std::mem::take(&mut fragment.includes);
+		config.add_blocks(fragment);
+		for include in includes {
+			let path = dir.join(include);
+			if let Some(fragment) = self.read(&path)? {
+				self.merge(config, fragment, &path)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Fragment {
+	/// Adds blocks from `when` nodes that apply to this machine, and drops the others.
+	fn resolve_conditionals(&mut self) {
+		for conditional in std::mem::take(&mut self.conditionals) {
+			if conditional.applies() {
+				self.includes.extend(conditional.includes);
+				self.categories.extend(conditional.categories);
+				self.schedules.extend(conditional.schedules);
+				self.rules.extend(conditional.rules);
+				self.permits.extend(conditional.permits);
+			}
+		}
+	}
+
+	/// Marks blocks with the file they come from, and reads files they refer to relative to its directory.
+	fn prepare(&mut self, source: usize, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ConfigError> {
+		self.categories.iter_mut().for_each(|category| category.source = source);
+		self.schedules.iter_mut().for_each(|schedule| schedule.source = source);
+		self.rules.iter_mut().for_each(|rule| rule.source = source);
+		self.permits.iter_mut().for_each(|permit| permit.source = source);
+		self.load_holidays(dir, files)?;
+		self.load_domains_files(dir, files)
+	}
+
+	/// Reads holiday files referenced by rule exceptions, resolving relative paths against the given directory.
+	fn load_holidays(&mut self, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ConfigError> {
+		for exception in self.rules.iter_mut().flat_map(|rule| &mut rule.exceptions) {
+			for path in &exception.holidays {
+				let dates = parse_file("holiday", &dir.join(path), files, holidays::parse)?;
+				exception.holiday_dates.extend(dates);
+			}
+		}
+		Ok(())
+	}
+
+	/// Reads domains files referenced by categories and their exceptions, resolving relative paths against the given
+	/// directory.
+	fn load_domains_files(&mut self, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ConfigError> {
+		for category in &mut self.categories {
+			for matchers in std::iter::once(&mut category.matchers).chain(&mut category.except) {
+				for file in &mut matchers.domains_files.0 {
+					let format = file.format;
+					let parse = |_: &Path, text: &str| blocklist::parse(format, text);
+					file.domains = parse_file("domains", &dir.join(&file.path), files, parse)?;
+				}
+			}
+		}
+		Ok(())
+	}
 }
 
-fn check_unique_names<T>(blocks: &[T], name: impl Fn(&T) -> &str) -> Result<(), ConfigError> {
-	let set: HashSet<&str> = blocks.iter().map(name).collect();
-	if set.len() != blocks.len() {
-		return Err(ConfigError::ValidationFailure { details: "blocks of the same type can't have identical names" });
+impl When {
+	fn applies(&self) -> bool {
+		let hostname = self.hostname.as_ref().is_none_or(|hostname| {
+			let current = std::fs::read_to_string("/proc/sys/kernel/hostname");
+			current.is_ok_and(|current| current.trim().eq_ignore_ascii_case(hostname))
+		});
+		let user = self.user.as_ref().is_none_or(|user| std::env::var("USER").is_ok_and(|current| current == *user));
+		let env = self.env.as_ref().is_none_or(|env| match env.split_once('=') {
+			Some((name, value)) => std::env::var_os(name).is_some_and(|current| current == value),
+			None => std::env::var_os(env).is_some(),
+		});
+		hostname && user && env
+	}
+}
+
+/// Reads a file the config depends on, remembering its canonical path so that the daemon can watch it.
+fn read_file(path: &Path, files: &mut Vec<PathBuf>) -> Result<String, ConfigError> {
+	let text =
+		std::fs::read_to_string(path).map_err(|error| ConfigError::ReadError { path: path.to_owned(), error })?;
+	files.push(path.canonicalize().unwrap_or_else(|_| path.to_owned()));
+	Ok(text)
+}
+
+/// Reads a file referenced by the config, reporting parse errors along with the kind of the file and its path.
+fn parse_file<T>(
+	kind: &'static str,
+	path: &Path,
+	files: &mut Vec<PathBuf>,
+	parse: impl FnOnce(&Path, &str) -> Result<T, LineError>,
+) -> Result<T, ConfigError> {
+	let text = read_file(path, files)?;
+	parse(path, &text).map_err(|LineError { line, details }| ConfigError::LineError {
+		kind,
+		path: path.to_owned(),
+		line,
+		details,
+	})
+}
+
+fn decode<T: DecodeChildren<Span>>(source: &Source) -> Result<T, ConfigError> {
+	knuffel::parse(&source.name, &source.text)
+		.map_err(|e| ConfigError::ParseError { diagnostics: diagnostics::parse_diagnostics(&e, source) })
+}
+
+/// Lists config files in the drop-in directory in alphabetical order, or nothing if the directory doesn't exist.
+fn drop_ins(dir: &Path) -> Result<Vec<PathBuf>, ConfigError> {
+	let read_error = |error| ConfigError::ReadError { path: dir.to_owned(), error };
+	let entries = match std::fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(read_error(e)),
+	};
+	let mut paths = Vec::new();
+	for entry in entries {
+		let path = entry.map_err(read_error)?.path();
+		if path.extension() == Some("kdl".as_ref()) {
+			paths.push(path);
+		}
+	}
+	paths.sort();
+	Ok(paths)
+}
+
+fn check_unique_names<T: Block>(blocks: &[T]) -> Result<(), Invalid> {
+	let mut names = HashMap::new();
+	for block in blocks {
+		if let Some(previous) = names.insert(block.name(), block) {
+			let details = "blocks of the same type can't have identical names";
+			let help = format!("rename one of the {} blocks", T::KIND);
+			return Err(Invalid::in_block(block, details, "is defined more than once").previous(previous).help(help));
+		}
 	}
 	Ok(())
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn example() {
 	let text = r#"
 prevent-browser-close
@@ -220,27 +824,29 @@ permit "example" {
 	assert_eq!(Duration::from(config.processes_scan_each), Duration::from_secs(10));
 	assert_eq!(config.categories.len(), 2);
 	assert_eq!(config.categories[0].name, "example");
-	assert_eq!(config.categories[0].domains, Some(vec!["example.com".to_string()]));
-	assert_eq!(config.categories[0].subreddits, Some(vec!["all".to_string()]));
-	assert_eq!(config.categories[0].githubs, Some(vec!["unneon/icie".to_string()]));
-	assert_eq!(config.categories[0].regexes, Some(vec!["example\\.org".to_string()]));
+	assert_eq!(config.categories[0].matchers.domains, Some(vec!["example.com".to_string()]));
+	assert_eq!(config.categories[0].matchers.sites.get("subreddits"), Some(&vec!["all".to_string()]));
+	assert_eq!(config.categories[0].matchers.sites.get("githubs"), Some(&vec!["unneon/icie".to_string()]));
+	assert_eq!(config.categories[0].matchers.regexes, Some(vec!["example\\.org".to_string()]));
 	assert_eq!(config.categories[0].processes, Some(vec!["chrome".to_string()]));
 	assert_eq!(config.categories[1].name, "other");
-	assert_eq!(config.categories[1].githubs, Some(vec!["unneon/vaxtify".to_string()]));
+	assert_eq!(config.categories[1].matchers.sites.get("githubs"), Some(&vec!["unneon/vaxtify".to_string()]));
 	assert_eq!(config.rules.len(), 2);
 	assert_eq!(config.rules[0].name, "things");
-	assert_eq!(config.rules[0].allowed.map(|r| NaiveTime::from(r.since)), Some(NaiveTime::from_hms(23, 30, 0)));
-	assert_eq!(config.rules[0].allowed.map(|r| NaiveTime::from(r.until)), Some(NaiveTime::from_hms(0, 0, 0)));
+	assert_eq!(config.rules[0].allowed.len(), 1);
+	assert_eq!(NaiveTime::from(config.rules[0].allowed[0].since), NaiveTime::from_hms(23, 30, 0));
+	assert_eq!(config.rules[0].allowed[0].until.map(NaiveTime::from), Some(NaiveTime::from_hms(0, 0, 0)));
 	assert_eq!(config.rules[0].categories, ["example"]);
 	assert_eq!(config.rules[1].name, "never");
-	assert_eq!(config.rules[1].allowed, None);
+	assert_eq!(config.rules[1].allowed, []);
 	assert_eq!(config.rules[1].categories, ["other"]);
 	assert_eq!(config.permits.len(), 1);
 	assert_eq!(config.permits[0].name, "example");
-	assert_eq!(Duration::from(config.permits[0].length), Duration::from_secs(30 * 60));
+	assert_eq!(config.permits[0].length.map(Duration::from), Some(Duration::from_secs(30 * 60)));
 	assert_eq!(config.permits[0].cooldown.map(Duration::from), Some(Duration::from_secs(20 * 60 * 60)));
-	assert_eq!(config.permits[0].available.map(|r| NaiveTime::from(r.since)), Some(NaiveTime::from_hms(20, 0, 0)));
-	assert_eq!(config.permits[0].available.map(|r| NaiveTime::from(r.until)), Some(NaiveTime::from_hms(0, 0, 0)));
+	assert_eq!(config.permits[0].available.len(), 1);
+	assert_eq!(NaiveTime::from(config.permits[0].available[0].since), NaiveTime::from_hms(20, 0, 0));
+	assert_eq!(config.permits[0].available[0].until.map(NaiveTime::from), Some(NaiveTime::from_hms(0, 0, 0)));
 	assert_eq!(config.permits[0].categories, ["other"]);
 }
 
@@ -297,10 +903,11 @@ permit "sometimes" {
 }
 
 #[cfg(test)]
+#[allow(clippy::collapsible_match)]
 fn assert_duplicate_error(text: &str) {
 	let result = Config::parse(text);
 	if let Err(e) = &result {
-		if let ConfigError::ValidationFailure { details } = e {
+		if let ConfigError::ValidationFailure { details, .. } = e {
 			assert_eq!(*details, "blocks of the same type can't have identical names");
 			return;
 		}
@@ -330,13 +937,465 @@ permit "sometimes" {
 }
 
 #[cfg(test)]
+#[allow(clippy::collapsible_match)]
 fn assert_category_does_not_exist_error(text: &str) {
 	let result = Config::parse(text);
 	if let Err(e) = &result {
-		if let ConfigError::ValidationFailure { details } = e {
+		if let ConfigError::ValidationFailure { details, .. } = e {
 			assert_eq!(*details, "category does not exist");
 			return;
 		}
 	}
 	panic!("{:?}", result);
 }
+
+#[test]
+fn diagnostics() {
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+rule "never" {
+	categories "socail"
+}
+"#;
+	match Config::parse(text) {
+		Err(ConfigError::ValidationFailure { diagnostic, .. }) => {
+			assert_eq!(diagnostic.location, Some(diagnostics::Location { line: 6, column: 1 }));
+			assert_eq!(diagnostic.message, "rule \"never\" refers to category \"socail\", which does not exist");
+			assert_eq!(diagnostic.help.as_deref(), Some("did you mean \"social\"?"));
+		}
+		result => panic!("{:?}", result),
+	}
+	match Config::parse("category \"social\" {\n\tdomain \"twitter.com\"\n}\n") {
+		Err(ConfigError::ParseError { diagnostics }) => {
+			assert_eq!(diagnostics.len(), 1);
+			assert_eq!(diagnostics[0].location, Some(diagnostics::Location { line: 2, column: 2 }));
+		}
+		result => panic!("{:?}", result),
+	}
+}
+
+#[test]
+fn seconds() {
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+rule "evening" {
+	allowed {
+		since hour=20 min=30 sec=15
+		until hour=21 sec=59
+	}
+	categories "social"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	assert_eq!(NaiveTime::from(config.rules[0].allowed[0].since), NaiveTime::from_hms(20, 30, 15));
+	assert_eq!(config.rules[0].allowed[0].until.map(NaiveTime::from), Some(NaiveTime::from_hms(21, 0, 59)));
+}
+
+#[test]
+fn invalid_regex_time_and_duration() {
+	let text = r#"
+category "social" {
+	regexes r"twitter\.com/(home"
+}
+"#;
+	let result = Config::parse(text);
+	assert!(matches!(result, Err(ConfigError::ValidationFailure { details: "invalid regex", .. })), "{:?}", result);
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+rule "evenings" {
+	allowed {
+		since hour=24
+	}
+	categories "social"
+}
+"#;
+	match Config::parse(text) {
+		Err(ConfigError::ParseError { diagnostics }) => {
+			assert_eq!(diagnostics.len(), 1);
+			assert_eq!(diagnostics[0].location, Some(diagnostics::Location { line: 8, column: 3 }));
+		}
+		result => panic!("{:?}", result),
+	}
+	let text = r#"
+permit "forever" {
+	length days=18446744073709551615
+	categories
+}
+"#;
+	assert!(matches!(Config::parse(text), Err(ConfigError::ParseError { .. })));
+}
+
+#[test]
+fn weekdays() {
+	use chrono::TimeZone;
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+rule "workweek" {
+	allowed {
+		days "sat" "sun"
+	}
+	categories "social"
+}
+
+permit "friday-evening" {
+	length mins=30
+	available {
+		since hour=20
+		until hour=2
+		days "fri"
+	}
+	categories "social"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let friday_evening = Local.ymd(2026, 10, 16).and_hms(21, 0, 0);
+	let saturday_night = Local.ymd(2026, 10, 17).and_hms(1, 0, 0);
+	let saturday_evening = Local.ymd(2026, 10, 17).and_hms(21, 0, 0);
+	assert!(config.rules[0].is_active(&friday_evening));
+	assert!(!config.rules[0].is_active(&saturday_night));
+	assert_eq!(config.rules[0].next_change_time(&friday_evening), Some(Local.ymd(2026, 10, 17).and_hms(0, 0, 0)));
+	assert_eq!(config.rules[0].next_change_time(&saturday_night), Some(Local.ymd(2026, 10, 18).and_hms(0, 0, 0)));
+	assert!(config.permits[0].is_available(&friday_evening));
+	assert!(config.permits[0].is_available(&saturday_night));
+	assert!(!config.permits[0].is_available(&saturday_evening));
+}
+
+#[test]
+fn equal_bounds() {
+	use chrono::TimeZone;
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+rule "never-allowed" {
+	allowed {
+		since hour=12
+		until hour=12
+	}
+	categories "social"
+}
+
+rule "always-allowed" {
+	allowed {
+	}
+	categories "social"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let noon = Local.ymd(2026, 10, 16).and_hms(12, 0, 0);
+	let midnight = Local.ymd(2026, 10, 17).and_hms(0, 0, 0);
+	assert!(config.rules[0].is_active(&noon));
+	assert!(config.rules[0].is_active(&midnight));
+	assert!(!config.rules[1].is_active(&noon));
+	assert!(!config.rules[1].is_active(&midnight));
+}
+
+#[test]
+fn weekdays_empty() {
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+rule "never" {
+	allowed {
+		days
+	}
+	categories "social"
+}
+"#;
+	let result = Config::parse(text);
+	if let Err(ConfigError::ValidationFailure { details, .. }) = &result {
+		assert_eq!(*details, "days must list at least one day");
+		return;
+	}
+	panic!("{:?}", result);
+}
+
+#[test]
+fn multiple_allowed() {
+	use chrono::TimeZone;
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+rule "lunch-and-evening" {
+	allowed {
+		since hour=12
+		until hour=13
+	}
+	allowed {
+		since hour=20
+	}
+	categories "social"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let rule = &config.rules[0];
+	assert!(rule.is_active(&Local.ymd(2026, 10, 16).and_hms(11, 0, 0)));
+	assert!(!rule.is_active(&Local.ymd(2026, 10, 16).and_hms(12, 30, 0)));
+	assert!(rule.is_active(&Local.ymd(2026, 10, 16).and_hms(15, 0, 0)));
+	assert!(!rule.is_active(&Local.ymd(2026, 10, 16).and_hms(22, 0, 0)));
+	let afternoon = Local.ymd(2026, 10, 16).and_hms(13, 30, 0);
+	assert_eq!(rule.next_change_time(&afternoon), Some(Local.ymd(2026, 10, 16).and_hms(20, 0, 0)));
+}
+
+#[test]
+fn exceptions() {
+	use chrono::TimeZone;
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+rule "workweek" {
+	allowed {
+		days "sat" "sun"
+	}
+	exception {
+		dates "2026-12-24..2026-12-25"
+	}
+	exception {
+		dates "2026-11-11"
+		like "sun"
+	}
+	categories "social"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let rule = &config.rules[0];
+	assert!(rule.is_active(&Local.ymd(2026, 12, 23).and_hms(12, 0, 0)));
+	assert!(!rule.is_active(&Local.ymd(2026, 12, 24).and_hms(12, 0, 0)));
+	assert!(!rule.is_active(&Local.ymd(2026, 11, 11).and_hms(12, 0, 0)));
+	assert!(rule.is_active(&Local.ymd(2026, 11, 12).and_hms(12, 0, 0)));
+	let christmas_eve = Local.ymd(2026, 12, 23).and_hms(12, 0, 0);
+	assert_eq!(rule.next_change_time(&christmas_eve), Some(Local.ymd(2026, 12, 24).and_hms(0, 0, 0)));
+}
+
+#[test]
+fn schedules() {
+	use chrono::TimeZone;
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+schedule "work-hours" {
+	window {
+		since hour=9
+		until hour=17
+		days "mon-fri"
+	}
+}
+
+rule "after-work" {
+	allowed {
+		since hour=22
+	}
+	allowed-schedules "work-hours"
+	categories "social"
+}
+
+permit "break" {
+	length mins=5
+	available-schedules "work-hours"
+	categories "social"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	assert_eq!(config.rules[0].allowed.len(), 2);
+	assert!(!config.rules[0].is_active(&Local.ymd(2026, 10, 16).and_hms(10, 0, 0)));
+	assert!(config.rules[0].is_active(&Local.ymd(2026, 10, 17).and_hms(10, 0, 0)));
+	assert!(config.permits[0].is_available(&Local.ymd(2026, 10, 16).and_hms(10, 0, 0)));
+	assert!(!config.permits[0].is_available(&Local.ymd(2026, 10, 16).and_hms(18, 0, 0)));
+}
+
+#[test]
+fn schedule_does_not_exist() {
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+rule "after-work" {
+	allowed-schedules "work-hours"
+	categories "social"
+}
+"#;
+	let result = Config::parse(text);
+	if let Err(ConfigError::ValidationFailure { details, .. }) = &result {
+		assert_eq!(*details, "schedule does not exist");
+		return;
+	}
+	panic!("{:?}", result);
+}
+
+#[test]
+fn schedule_empty() {
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+schedule "work-hours" {
+}
+
+rule "after-work" {
+	allowed-schedules "work-hours"
+	categories "social"
+}
+"#;
+	let result = Config::parse(text);
+	if let Err(ConfigError::ValidationFailure { details, .. }) = &result {
+		assert_eq!(*details, "schedule must have at least one window");
+		return;
+	}
+	panic!("{:?}", result);
+}
+
+#[test]
+fn category_includes_itself() {
+	let text = r#"
+category "leisure" {
+	includes "video"
+}
+
+category "video" {
+	domains "www.youtube.com"
+	includes "social"
+}
+
+category "social" {
+	includes "leisure"
+}
+"#;
+	let result = Config::parse(text);
+	if let Err(ConfigError::ValidationFailure { details, .. }) = &result {
+		assert_eq!(*details, "categories can't include themselves");
+		return;
+	}
+	panic!("{:?}", result);
+}
+
+#[test]
+fn category_in_includes_does_not_exist() {
+	let text = r#"
+category "leisure" {
+	includes "video"
+}
+"#;
+	assert_category_does_not_exist_error(text);
+}
+
+#[test]
+fn missing_file() {
+	let path = std::env::temp_dir().join(format!("vaxtify-missing-test-{}.kdl", std::process::id()));
+	let error = Config::load_file(&path).unwrap_err().to_string();
+	assert!(error.starts_with(&format!("failed to read {:?} (No such file or directory", path)), "{}", error);
+}
+
+#[test]
+fn includes_and_drop_ins() {
+	let dir = std::env::temp_dir().join(format!("vaxtify-test-{}", std::process::id()));
+	std::fs::create_dir_all(dir.join("vaxtify.d")).unwrap();
+	let main = "category \"social\" {\n\tdomains \"twitter.com\"\n}\n\ninclude \"shared.kdl\"\n";
+	std::fs::write(dir.join("vaxtify.kdl"), main).unwrap();
+	std::fs::write(dir.join("shared.kdl"), "rule \"always\" {\n\tcategories \"social\" \"personal\"\n}\n").unwrap();
+	std::fs::write(dir.join("vaxtify.d/personal.kdl"), "category \"personal\" {\n\tdomains \"example.com\"\n}\n")
+		.unwrap();
+	std::fs::write(dir.join("vaxtify.d/notes.txt"), "not a config").unwrap();
+	let config = Config::load_file(&dir.join("vaxtify.kdl")).unwrap();
+	assert_eq!(config.categories.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["social", "personal"]);
+	assert_eq!(config.rules[0].categories, ["social", "personal"]);
+	std::fs::write(dir.join("vaxtify.d/social.kdl"), "category \"social\" {\n}\n").unwrap();
+	let result = Config::load_file(&dir.join("vaxtify.kdl"));
+	std::fs::remove_dir_all(&dir).unwrap();
+	match result {
+		Err(ConfigError::ValidationFailure { diagnostic, .. }) => {
+			assert_eq!(diagnostic.file, dir.join("vaxtify.d/social.kdl").display().to_string());
+			let first = format!("first defined at {}:1:1", dir.join("vaxtify.kdl").display());
+			assert!(diagnostic.message.ends_with(&first), "{}", diagnostic.message);
+		}
+		result => panic!("{:?}", result),
+	}
+}
+
+#[test]
+fn conditionals() {
+	let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap();
+	let text = format!(
+		r#"
+category "social" {{
+	domains "twitter.com"
+}}
+
+when hostname="{}" env="PATH" {{
+	rule "here" {{
+		categories "social"
+	}}
+}}
+
+when hostname="{}.elsewhere" {{
+	rule "here" {{
+		categories "missing"
+	}}
+}}
+
+when env="PATH=nonsense" {{
+	rule "elsewhere" {{
+		categories "social"
+	}}
+}}
+"#,
+		hostname.trim(),
+		hostname.trim()
+	);
+	let config = Config::parse(&text).unwrap();
+	assert_eq!(config.rules.len(), 1);
+	assert_eq!(config.rules[0].name, "here");
+	assert_eq!(config.rules[0].categories, ["social"]);
+}
+
+#[test]
+fn permit_lengths() {
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+permit "unbounded" {
+	categories "social"
+}
+"#;
+	let result = Config::parse(text);
+	let details = "permit must have a length";
+	assert!(matches!(result, Err(ConfigError::ValidationFailure { details: d, .. }) if d == details), "{:?}", result);
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+permit "inverted" {
+	length mins=30
+	max-length mins=10
+	categories "social"
+}
+"#;
+	let result = Config::parse(text);
+	let details = "permit length can't be longer than its max-length";
+	assert!(matches!(result, Err(ConfigError::ValidationFailure { details: d, .. }) if d == details), "{:?}", result);
+}
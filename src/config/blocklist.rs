@@ -0,0 +1,143 @@
+use crate::config::diagnostics::LineError;
+use crate::config::normalize_domain_pattern;
+use knuffel::ast::{SpannedName, SpannedNode, Value};
+use knuffel::decode::Context;
+use knuffel::errors::DecodeError;
+use knuffel::traits::{DecodePartial, ErrorSpan};
+use knuffel::Decode;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// External list of domains, written as `domains-file "hosts.txt" format="hosts"`.
+#[derive(Debug, Decode)]
+pub struct DomainsFile {
+	#[knuffel(argument)]
+	pub path: String,
+	#[knuffel(property, str, default)]
+	pub format: Format,
+	pub domains: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Format {
+	/// One domain pattern per line, like in the `domains` node.
+	#[default]
+	List,
+	/// Hosts file, with an IP address followed by the domains redirected to it.
+	Hosts,
+	/// Adblock filter list, of which only `||example.com^` rules blocking a domain and its subdomains are used.
+	Adblock,
+}
+
+/// All `domains-file` nodes of a category or its exceptions.
+#[derive(Debug, Default)]
+pub struct DomainsFiles(pub Vec<DomainsFile>);
+
+pub fn parse(format: Format, text: &str) -> Result<Vec<String>, LineError> {
+	let mut domains = Vec::new();
+	for (index, line) in text.lines().enumerate() {
+		let error = |details: String| LineError { line: index + 1, details };
+		let line = match format {
+			Format::List | Format::Hosts => line.split('#').next().unwrap(),
+			Format::Adblock => line,
+		};
+		let line = line.trim();
+		if line.is_empty() {
+			continue;
+		}
+		match format {
+			Format::List => domains.push(parse_domain(line).map_err(error)?),
+			Format::Hosts => {
+				let mut fields = line.split_whitespace();
+				let address = fields.next().unwrap();
+				address.parse::<IpAddr>().map_err(|_| error(format!("invalid IP address {:?}", address)))?;
+				for host in fields.filter(|host| !LOCAL_HOSTS.contains(host)) {
+					domains.push(parse_domain(host).map_err(error)?);
+				}
+			}
+			Format::Adblock => {
+				if let Some(domain) = line.strip_prefix("||").and_then(|rule| rule.strip_suffix('^')) {
+					domains.push(parse_domain(&format!(".{}", domain)).map_err(error)?);
+				}
+			}
+		}
+	}
+	Ok(domains)
+}
+
+/// Names of the local machine present in most hosts files, which should not be blocked.
+const LOCAL_HOSTS: &[&str] =
+	&["localhost", "localhost.localdomain", "local", "broadcasthost", "ip6-localhost", "ip6-loopback", "0.0.0.0"];
+
+fn parse_domain(pattern: &str) -> Result<String, String> {
+	normalize_domain_pattern(pattern).ok_or_else(|| format!("invalid domain {:?}", pattern))
+}
+
+impl FromStr for Format {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"list" => Ok(Format::List),
+			"hosts" => Ok(Format::Hosts),
+			"adblock" => Ok(Format::Adblock),
+			_ => Err(format!("invalid domains file format {:?}", s)),
+		}
+	}
+}
+
+impl<S: ErrorSpan> DecodePartial<S> for DomainsFiles {
+	fn insert_child(&mut self, node: &SpannedNode<S>, ctx: &mut Context<S>) -> Result<bool, DecodeError<S>> {
+		if &**node.node_name != "domains-file" {
+			return Ok(false);
+		}
+		self.0.push(DomainsFile::decode_node(node, ctx)?);
+		Ok(true)
+	}
+
+	fn insert_property(
+		&mut self,
+		_: &SpannedName<S>,
+		_: &Value<S>,
+		_: &mut Context<S>,
+	) -> Result<bool, DecodeError<S>> {
+		Ok(false)
+	}
+}
+
+#[test]
+fn hosts() {
+	let text = "# Blocklist\n127.0.0.1 localhost\n0.0.0.0 Example.com www.example.com # ads\n\n::1 ip6-localhost\n";
+	assert_eq!(parse(Format::Hosts, text).unwrap(), ["example.com", "www.example.com"]);
+	let error = parse(Format::Hosts, "0.0.0.0 example.com\nexample.org\n").unwrap_err();
+	assert_eq!(error.line, 2);
+}
+
+#[test]
+fn adblock() {
+	let text =
+		"[Adblock Plus 2.0]\n! Title: Example\n||example.com^\n||ads.example.org^$third-party\nexample.net##.ad\n";
+	assert_eq!(parse(Format::Adblock, text).unwrap(), [".example.com"]);
+}
+
+#[test]
+fn list() {
+	assert_eq!(parse(Format::List, "example.com\n*.example.org\n").unwrap(), ["example.com", "*.example.org"]);
+	assert_eq!(parse(Format::List, "example.com\nnot a domain\n").unwrap_err().line, 2);
+}
+
+#[test]
+fn decode() {
+	let text = r#"
+category "ads" {
+	domains-file "hosts.txt" format="hosts"
+	domains-file "domains.txt"
+}
+"#;
+	let config = crate::config::Config::parse(text).unwrap();
+	let files = &config.categories[0].matchers.domains_files.0;
+	assert_eq!(
+		files.iter().map(|file| (file.path.as_str(), file.format)).collect::<Vec<_>>(),
+		[("hosts.txt", Format::Hosts), ("domains.txt", Format::List)]
+	);
+}
@@ -0,0 +1,199 @@
+use crate::config::{Category, Permit, Rule, Schedule};
+use knuffel::span::Span;
+use miette::Diagnostic as _;
+use std::fmt;
+
+/// Problem found in the config file, pointing at the relevant place if there is one.
+#[derive(Debug)]
+pub struct Diagnostic {
+	pub file: String,
+	pub location: Option<Location>,
+	pub message: String,
+	pub help: Option<String>,
+}
+
+/// Position in the config file, with 1-based line and column numbers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Location {
+	pub line: usize,
+	pub column: usize,
+}
+
+/// Error found at a given (1-based) line of a holiday or domains file.
+#[derive(Debug)]
+pub struct LineError {
+	pub line: usize,
+	pub details: String,
+}
+
+/// Config file text, kept so that diagnostics can point into any of the files merged into one config.
+pub struct Source {
+	pub name: String,
+	pub text: String,
+}
+
+/// Validation error found before its position in the file is known, turned into a diagnostic once the config is
+/// merged.
+pub struct Invalid {
+	pub details: &'static str,
+	message: String,
+	/// Source file index and offset in it.
+	offset: Option<(usize, usize)>,
+	previous: Option<(usize, usize)>,
+	help: Option<String>,
+}
+
+/// Named top-level block, used to point at the block in validation errors.
+pub trait Block {
+	const KIND: &'static str;
+
+	fn name(&self) -> &str;
+
+	fn span(&self) -> &Span;
+
+	/// Index of the source file the block was read from.
+	fn source(&self) -> usize;
+}
+
+impl Invalid {
+	pub fn new(details: &'static str, message: String) -> Invalid {
+		Invalid { details, message, offset: None, previous: None, help: None }
+	}
+
+	/// Creates an error about the given block, with the message prefixed by the block kind and name.
+	pub fn in_block<T: Block>(block: &T, details: &'static str, message: impl fmt::Display) -> Invalid {
+		let message = format!("{} {:?} {}", T::KIND, block.name(), message);
+		Invalid { details, message, offset: Some((block.source(), block.span().0)), previous: None, help: None }
+	}
+
+	/// Mentions where an earlier block that conflicts with this one was defined.
+	pub fn previous<T: Block>(self, block: &T) -> Invalid {
+		Invalid { previous: Some((block.source(), block.span().0)), ..self }
+	}
+
+	pub fn help(self, help: impl Into<String>) -> Invalid {
+		Invalid { help: Some(help.into()), ..self }
+	}
+
+	pub fn locate(self, sources: &[Source]) -> Diagnostic {
+		let mut message = self.message;
+		if let Some((source, offset)) = self.previous {
+			let source = &sources[source];
+			let Location { line, column } = Location::new(&source.text, offset);
+			message = format!("{}, first defined at {}:{}:{}", message, source.name, line, column);
+		}
+		let (source, location) = match self.offset {
+			Some((source, offset)) => (&sources[source], Some(Location::new(&sources[source].text, offset))),
+			None => (&sources[0], None),
+		};
+		Diagnostic { file: source.name.clone(), location, message, help: self.help }
+	}
+}
+
+impl Location {
+	fn new(text: &str, offset: usize) -> Location {
+		let before = &text[..offset.min(text.len())];
+		let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+		Location { line: before.matches('\n').count() + 1, column: before[line_start..].chars().count() + 1 }
+	}
+}
+
+/// Converts knuffel errors, which already know their spans and often come with a help message.
+pub fn parse_diagnostics(error: &knuffel::Error, source: &Source) -> Vec<Diagnostic> {
+	let related = match error.related() {
+		Some(related) => related,
+		None => {
+			return vec![Diagnostic {
+				file: source.name.clone(),
+				location: None,
+				message: error.to_string(),
+				help: None,
+			}]
+		}
+	};
+	related
+		.map(|error| {
+			let label = error.labels().and_then(|mut labels| labels.next());
+			Diagnostic {
+				file: source.name.clone(),
+				location: label.map(|label| Location::new(&source.text, label.offset())),
+				message: error.to_string(),
+				help: error.help().map(|help| help.to_string()),
+			}
+		})
+		.collect()
+}
+
+/// Suggests the most similar existing name, if any is close enough to be a typo.
+pub fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<String> {
+	let max_distance = (name.chars().count() / 3).max(1);
+	let (distance, candidate) = candidates.map(|candidate| (edit_distance(name, candidate), candidate)).min()?;
+	if distance > max_distance {
+		return None;
+	}
+	Some(format!("did you mean {:?}?", candidate))
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b = b.chars().collect::<Vec<_>>();
+	let mut previous = (0..=b.len()).collect::<Vec<_>>();
+	for (i, a) in a.chars().enumerate() {
+		let mut current = vec![i + 1];
+		for (j, b) in b.iter().enumerate() {
+			let substitution = previous[j] + if a == *b { 0 } else { 1 };
+			current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+		}
+		previous = current;
+	}
+	previous[b.len()]
+}
+
+pub fn list(diagnostics: &[Diagnostic]) -> String {
+	diagnostics.iter().map(Diagnostic::to_string).collect::<Vec<_>>().join("; ")
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.location {
+			Some(Location { line, column }) => write!(f, "{}:{}:{}: {}", self.file, line, column, self.message)?,
+			None => write!(f, "{}: {}", self.file, self.message)?,
+		}
+		if let Some(help) = &self.help {
+			write!(f, " (help: {})", help)?;
+		}
+		Ok(())
+	}
+}
+
+macro_rules! impl_block {
+	($type:ty, $kind:literal) => {
+		impl Block for $type {
+			const KIND: &'static str = $kind;
+
+			fn name(&self) -> &str {
+				&self.name
+			}
+
+			fn span(&self) -> &Span {
+				&self.span
+			}
+
+			fn source(&self) -> usize {
+				self.source
+			}
+		}
+	};
+}
+
+impl_block!(Category, "category");
+impl_block!(Schedule, "schedule");
+impl_block!(Rule, "rule");
+impl_block!(Permit, "permit");
+
+#[test]
+fn suggestions() {
+	let names = ["social", "video", "news"];
+	assert_eq!(suggest("socail", names.iter().copied()).as_deref(), Some("did you mean \"social\"?"));
+	assert_eq!(suggest("games", names.iter().copied()), None);
+	assert_eq!(Location::new("a\nbc\nd", 4), Location { line: 2, column: 3 });
+}
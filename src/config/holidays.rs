@@ -0,0 +1,191 @@
+use crate::config::diagnostics::LineError;
+use crate::config::kdl_date::DateRange;
+use chrono::NaiveDate;
+use std::path::Path;
+
+pub fn parse(path: &Path, text: &str) -> Result<Vec<DateRange>, LineError> {
+	match path.extension().and_then(|ext| ext.to_str()) {
+		Some("ics") | Some("ical") => parse_ical(text),
+		_ => parse_csv(text),
+	}
+}
+
+/// Reads all-day and timed events from an iCalendar file, ignoring everything except their start and end dates.
+fn parse_ical(text: &str) -> Result<Vec<DateRange>, LineError> {
+	let mut ranges = Vec::new();
+	let mut in_event = false;
+	let mut start = None;
+	let mut end = None;
+	for (index, line) in unfold_ical(text) {
+		let error = |details: &str| LineError { line: index + 1, details: details.to_owned() };
+		if line == "BEGIN:VEVENT" {
+			in_event = true;
+			start = None;
+			end = None;
+		} else if !in_event {
+			// Other components, like time zone definitions, have their own start dates and recurrence rules.
+			continue;
+		} else if line == "END:VEVENT" {
+			in_event = false;
+			let (first, _) = start.ok_or_else(|| error("event has no DTSTART"))?;
+			let last = match end {
+				// DTEND is exclusive, so events ending exactly at midnight don't include the day they end on.
+				Some((end, true)) if end > first => end - chrono::Duration::days(1),
+				Some((end, _)) => end,
+				None => first,
+			};
+			ranges.push(DateRange { first, last });
+		} else if let Some(value) = ical_property(&line, "DTSTART") {
+			start = Some(parse_ical_date(value).ok_or_else(|| error("invalid DTSTART"))?);
+		} else if let Some(value) = ical_property(&line, "DTEND") {
+			end = Some(parse_ical_date(value).ok_or_else(|| error("invalid DTEND"))?);
+		} else if ical_property(&line, "RRULE").is_some() {
+			return Err(error("recurring events are not supported, list each occurrence separately"));
+		}
+	}
+	Ok(ranges)
+}
+
+/// Joins lines folded with a leading space or tab, returning each one with the (0-based) index of its first line.
+fn unfold_ical(text: &str) -> Vec<(usize, String)> {
+	let mut lines: Vec<(usize, String)> = Vec::new();
+	for (index, line) in text.lines().enumerate() {
+		let line = line.trim_end_matches('\r');
+		match (line.strip_prefix(|c| c == ' ' || c == '\t'), lines.last_mut()) {
+			(Some(continuation), Some((_, last))) => last.push_str(continuation),
+			_ => lines.push((index, line.to_owned())),
+		}
+	}
+	lines
+}
+
+fn ical_property<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+	let rest = line.strip_prefix(name)?;
+	if !rest.starts_with(':') && !rest.starts_with(';') {
+		return None;
+	}
+	Some(&rest[rest.find(':')? + 1..])
+}
+
+/// Parses a date or date-time value, returning the date and whether it starts exactly at midnight.
+fn parse_ical_date(value: &str) -> Option<(NaiveDate, bool)> {
+	let date = NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()?;
+	let midnight = match value.get(8..) {
+		Some("") => true,
+		Some(time) => time.trim_end_matches('Z').trim_start_matches('T').trim_start_matches('0').is_empty(),
+		None => false,
+	};
+	Some((date, midnight))
+}
+
+/// Reads dates or date ranges from the first column of each line, skipping empty lines and an optional header.
+fn parse_csv(text: &str) -> Result<Vec<DateRange>, LineError> {
+	let mut ranges = Vec::new();
+	for (index, line) in text.lines().enumerate() {
+		let column = line.split(',').next().unwrap().trim().trim_matches('"');
+		if column.is_empty() || column.starts_with('#') {
+			continue;
+		}
+		match column.parse() {
+			Ok(range) => ranges.push(range),
+			Err(_) if index == 0 => {}
+			Err(details) => return Err(LineError { line: index + 1, details }),
+		}
+	}
+	Ok(ranges)
+}
+
+#[test]
+fn ical() {
+	let text = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20261224\r
+DTEND;VALUE=DATE:20261227\r
+SUMMARY:Christmas\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20270101\r
+SUMMARY:New Year\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+	let ranges = parse_ical(text).unwrap();
+	assert_eq!(
+		ranges,
+		[
+			DateRange { first: NaiveDate::from_ymd(2026, 12, 24), last: NaiveDate::from_ymd(2026, 12, 26) },
+			DateRange { first: NaiveDate::from_ymd(2027, 1, 1), last: NaiveDate::from_ymd(2027, 1, 1) },
+		]
+	);
+}
+
+#[test]
+fn ical_folding_and_times() {
+	let text = "BEGIN:VEVENT\r
+DTSTART:20261223T180000Z\r
+DTEND:2026\r
+ 1226T120000Z\r
+SUMMARY:Long trip\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART:20261230T100000\r
+DTEND:20261231T000000\r
+END:VEVENT\r
+";
+	let ranges = parse_ical(text).unwrap();
+	assert_eq!(
+		ranges,
+		[
+			DateRange { first: NaiveDate::from_ymd(2026, 12, 23), last: NaiveDate::from_ymd(2026, 12, 26) },
+			DateRange { first: NaiveDate::from_ymd(2026, 12, 30), last: NaiveDate::from_ymd(2026, 12, 30) },
+		]
+	);
+}
+
+#[test]
+fn ical_recurring() {
+	let text = "BEGIN:VEVENT\nDTSTART;VALUE=DATE:20261224\nRRULE:FREQ=YEARLY\nEND:VEVENT\n";
+	let error = parse_ical(text).unwrap_err();
+	assert_eq!(error.line, 3);
+}
+
+#[test]
+fn ical_time_zone() {
+	let text = "BEGIN:VCALENDAR\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Warsaw\r
+BEGIN:STANDARD\r
+DTSTART:19701025T030000\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+END:STANDARD\r
+BEGIN:DAYLIGHT\r
+DTSTART:19700329T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r
+TZOFFSETFROM:+0100\r
+TZOFFSETTO:+0200\r
+END:DAYLIGHT\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20261111\r
+DTEND;VALUE=DATE:20261112\r
+SUMMARY:Independence Day\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+	let ranges = parse_ical(text).unwrap();
+	assert_eq!(
+		ranges,
+		[DateRange { first: NaiveDate::from_ymd(2026, 11, 11), last: NaiveDate::from_ymd(2026, 11, 11) }]
+	);
+}
+
+#[test]
+fn csv() {
+	let text = "date,name\n2026-12-24..2026-12-26,Christmas\n\n2027-01-01,New Year\nsoon,Invalid\n";
+	let error = parse_csv(text).unwrap_err();
+	assert_eq!(error.line, 5);
+	let ranges = parse_csv(&text[..text.find("soon").unwrap()]).unwrap();
+	assert_eq!(ranges.len(), 2);
+}
@@ -0,0 +1,43 @@
+use chrono::NaiveDate;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DateRange {
+	pub first: NaiveDate,
+	pub last: NaiveDate,
+}
+
+impl DateRange {
+	pub fn contains(&self, date: NaiveDate) -> bool {
+		self.first <= date && date <= self.last
+	}
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate, String> {
+	NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| format!("invalid date {:?}, expected YYYY-MM-DD", s))
+}
+
+impl FromStr for DateRange {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (first, last) = match s.split_once("..") {
+			Some((first, last)) => (parse_date(first)?, parse_date(last)?),
+			None => (parse_date(s)?, parse_date(s)?),
+		};
+		if first > last {
+			return Err(format!("date range {:?} ends before it starts", s));
+		}
+		Ok(DateRange { first, last })
+	}
+}
+
+#[test]
+fn parse() {
+	let christmas = "2026-12-24..2026-12-26".parse::<DateRange>().unwrap();
+	assert_eq!(christmas.first, NaiveDate::from_ymd(2026, 12, 24));
+	assert_eq!(christmas.last, NaiveDate::from_ymd(2026, 12, 26));
+	let new_year = "2027-01-01".parse::<DateRange>().unwrap();
+	assert_eq!(new_year.first, new_year.last);
+	assert!("2026-12-26..2026-12-24".parse::<DateRange>().is_err());
+}
@@ -1,7 +1,19 @@
+use knuffel::ast::SpannedNode;
+use knuffel::decode::Context;
+use knuffel::errors::DecodeError;
+use knuffel::traits::ErrorSpan;
 use knuffel::Decode;
 
-#[derive(Clone, Copy, Debug, Decode)]
+/// Durations are added to the current time, so anything longer would risk overflowing chrono's date range.
+const MAX_SECONDS: u64 = 100 * 365 * 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Duration {
+	seconds: u64,
+}
+
+#[derive(Decode)]
+struct Fields {
 	#[knuffel(property, default)]
 	days: u64,
 	#[knuffel(property, default)]
@@ -12,24 +24,27 @@ pub struct Duration {
 	seconds: u64,
 }
 
-impl PartialEq for Duration {
-	fn eq(&self, other: &Self) -> bool {
-		std::time::Duration::from(*self) == std::time::Duration::from(*other)
+impl<S: ErrorSpan> Decode<S> for Duration {
+	fn decode_node(node: &SpannedNode<S>, ctx: &mut Context<S>) -> Result<Self, DecodeError<S>> {
+		let Fields { days, hours, mins, seconds } = Fields::decode_node(node, ctx)?;
+		let hours = days.checked_mul(24).and_then(|days| days.checked_add(hours));
+		let mins = hours.and_then(|hours| hours.checked_mul(60)).and_then(|hours| hours.checked_add(mins));
+		let seconds = mins.and_then(|mins| mins.checked_mul(60)).and_then(|mins| mins.checked_add(seconds));
+		match seconds {
+			Some(seconds) if seconds <= MAX_SECONDS => Ok(Duration { seconds }),
+			_ => Err(DecodeError::conversion(node, "duration is too long, the maximum is 100 years")),
+		}
 	}
 }
 
 impl From<std::time::Duration> for Duration {
 	fn from(duration: std::time::Duration) -> Self {
-		Duration { days: 0, hours: 0, mins: 0, seconds: duration.as_secs() }
+		Duration { seconds: duration.as_secs() }
 	}
 }
 
 impl From<Duration> for std::time::Duration {
 	fn from(duration: Duration) -> Self {
-		let days = duration.days;
-		let hours = days * 24 + duration.hours;
-		let mins = hours * 60 + duration.mins;
-		let seconds = mins * 60 + duration.seconds;
-		std::time::Duration::from_secs(seconds)
+		std::time::Duration::from_secs(duration.seconds)
 	}
 }
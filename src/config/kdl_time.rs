@@ -1,15 +1,47 @@
+use knuffel::ast::SpannedNode;
+use knuffel::decode::Context;
+use knuffel::errors::DecodeError;
+use knuffel::traits::ErrorSpan;
 use knuffel::Decode;
 
-#[derive(Clone, Copy, Debug, Decode, Eq, PartialEq)]
+/// Time of day, checked to be valid when decoded so that converting it to chrono never panics.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct NaiveTime {
+	hour: u32,
+	min: u32,
+	sec: u32,
+}
+
+#[derive(Decode)]
+struct Fields {
 	#[knuffel(property)]
 	hour: u32,
 	#[knuffel(property, default)]
 	min: u32,
+	#[knuffel(property, default)]
+	sec: u32,
+}
+
+impl NaiveTime {
+	pub const MIDNIGHT: NaiveTime = NaiveTime { hour: 0, min: 0, sec: 0 };
+}
+
+impl<S: ErrorSpan> Decode<S> for NaiveTime {
+	fn decode_node(node: &SpannedNode<S>, ctx: &mut Context<S>) -> Result<Self, DecodeError<S>> {
+		let Fields { hour, min, sec } = Fields::decode_node(node, ctx)?;
+		if hour >= 24 || min >= 60 || sec >= 60 {
+			let message = format!(
+				"invalid time {:02}:{:02}:{:02}, hours must be below 24 and minutes and seconds below 60",
+				hour, min, sec
+			);
+			return Err(DecodeError::conversion(node, message));
+		}
+		Ok(NaiveTime { hour, min, sec })
+	}
 }
 
 impl From<NaiveTime> for chrono::NaiveTime {
 	fn from(time: NaiveTime) -> Self {
-		chrono::NaiveTime::from_hms(time.hour, time.min, 0)
+		chrono::NaiveTime::from_hms(time.hour, time.min, time.sec)
 	}
 }
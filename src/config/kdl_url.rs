@@ -0,0 +1,70 @@
+use crate::filters;
+use std::str::FromStr;
+
+/// Path prefix, optionally limited to a domain, written as `www.youtube.com/shorts` or `/shorts`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PathPrefix {
+	pub domain: Option<String>,
+	pub prefix: String,
+}
+
+/// Query parameter with an optional value and domain, written as `www.youtube.com?list=PL123`, `list=PL123` or `list`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueryParam {
+	pub domain: Option<String>,
+	pub name: String,
+	pub value: Option<String>,
+}
+
+fn parse_domain(domain: &str) -> Result<String, String> {
+	filters::normalize_domain(domain).ok_or_else(|| format!("invalid domain {:?}", domain))
+}
+
+impl FromStr for PathPrefix {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.find('/') {
+			Some(0) => Ok(PathPrefix { domain: None, prefix: s.to_owned() }),
+			Some(slash) => Ok(PathPrefix { domain: Some(parse_domain(&s[..slash])?), prefix: s[slash..].to_owned() }),
+			None => Err(format!("path prefix {:?} does not contain a path", s)),
+		}
+	}
+}
+
+impl FromStr for QueryParam {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (domain, param) = match s.split_once('?') {
+			Some((domain, param)) => (Some(parse_domain(domain)?), param),
+			None => (None, s),
+		};
+		let (name, value) = match param.split_once('=') {
+			Some((name, value)) => (name, Some(value.to_owned())),
+			None => (param, None),
+		};
+		if name.is_empty() {
+			return Err(format!("query parameter {:?} has no name", s));
+		}
+		Ok(QueryParam { domain, name: name.to_owned(), value })
+	}
+}
+
+#[test]
+fn path_prefix() {
+	let shorts = "WWW.YouTube.com/shorts".parse::<PathPrefix>().unwrap();
+	assert_eq!(shorts, PathPrefix { domain: Some("www.youtube.com".to_owned()), prefix: "/shorts".to_owned() });
+	assert_eq!("/shorts".parse::<PathPrefix>().unwrap().domain, None);
+	assert!("www.youtube.com".parse::<PathPrefix>().is_err());
+}
+
+#[test]
+fn query_param() {
+	let list = "www.youtube.com?list=PL123".parse::<QueryParam>().unwrap();
+	assert_eq!(list.domain.as_deref(), Some("www.youtube.com"));
+	assert_eq!(list.name, "list");
+	assert_eq!(list.value.as_deref(), Some("PL123"));
+	assert_eq!("list".parse::<QueryParam>().unwrap().value, None);
+	assert!("www.youtube.com?".parse::<QueryParam>().is_err());
+}
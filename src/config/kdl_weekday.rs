@@ -0,0 +1,114 @@
+use std::fmt;
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Weekday(chrono::Weekday);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Weekdays {
+	mask: u8,
+}
+
+impl Weekdays {
+	pub const ALL: Weekdays = Weekdays { mask: 0b111_1111 };
+
+	pub fn contains(&self, weekday: chrono::Weekday) -> bool {
+		self.mask & bit(weekday) != 0
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.mask == 0
+	}
+
+	/// Returns the set of days directly following the days in this set, useful for ranges crossing midnight.
+	pub fn succ(&self) -> Weekdays {
+		Weekdays { mask: ((self.mask << 1) | (self.mask >> 6)) & Weekdays::ALL.mask }
+	}
+}
+
+fn bit(weekday: chrono::Weekday) -> u8 {
+	1 << weekday.num_days_from_monday()
+}
+
+fn parse_weekday(s: &str) -> Result<chrono::Weekday, String> {
+	s.parse().map_err(|_| format!("invalid weekday {:?}", s))
+}
+
+impl From<Weekday> for chrono::Weekday {
+	fn from(weekday: Weekday) -> Self {
+		weekday.0
+	}
+}
+
+impl FromStr for Weekday {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		parse_weekday(s).map(Weekday)
+	}
+}
+
+impl FromStr for Weekdays {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (first, last) = match s.split_once('-') {
+			Some((first, last)) => (parse_weekday(first)?, parse_weekday(last)?),
+			None => (parse_weekday(s)?, parse_weekday(s)?),
+		};
+		let mut weekdays = Weekdays { mask: bit(first) };
+		let mut day = first;
+		while day != last {
+			day = day.succ();
+			weekdays.mask |= bit(day);
+		}
+		Ok(weekdays)
+	}
+}
+
+impl FromIterator<Weekdays> for Weekdays {
+	fn from_iter<T: IntoIterator<Item = Weekdays>>(iter: T) -> Self {
+		Weekdays { mask: iter.into_iter().fold(0, |mask, weekdays| mask | weekdays.mask) }
+	}
+}
+
+impl fmt::Display for Weekdays {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut day = chrono::Weekday::Mon;
+		let mut first = true;
+		for _ in 0..7 {
+			if self.contains(day) {
+				if !first {
+					f.write_str(", ")?;
+				}
+				write!(f, "{}", day)?;
+				first = false;
+			}
+			day = day.succ();
+		}
+		Ok(())
+	}
+}
+
+#[test]
+fn parse_single() {
+	assert_eq!("sat".parse::<Weekdays>().unwrap(), Weekdays { mask: 0b010_0000 });
+}
+
+#[test]
+fn parse_range() {
+	assert_eq!("mon-fri".parse::<Weekdays>().unwrap(), Weekdays { mask: 0b001_1111 });
+	assert_eq!("sat-mon".parse::<Weekdays>().unwrap(), Weekdays { mask: 0b110_0001 });
+}
+
+#[test]
+fn parse_invalid() {
+	assert!("someday".parse::<Weekdays>().is_err());
+	assert!("mon-".parse::<Weekdays>().is_err());
+}
+
+#[test]
+fn succ_wraps() {
+	assert_eq!(Weekdays { mask: 0b100_0001 }.succ(), Weekdays { mask: 0b000_0011 });
+}
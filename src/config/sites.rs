@@ -0,0 +1,67 @@
+use crate::filters::EXTRACTORS;
+use knuffel::ast::{SpannedName, SpannedNode, Value};
+use knuffel::decode::Context;
+use knuffel::errors::DecodeError;
+use knuffel::traits::{DecodePartial, ErrorSpan};
+use knuffel::Decode;
+
+/// Keys matched by site extractors, such as `subreddits "funny"`, stored in the same order as the extractors.
+#[derive(Debug)]
+pub struct Sites(Vec<Option<Vec<String>>>);
+
+#[derive(Decode)]
+struct Keys(#[knuffel(arguments)] Vec<String>);
+
+impl Sites {
+	/// Iterates over keys listed for each extractor, in the same order as the extractors.
+	pub fn iter(&self) -> impl Iterator<Item = &[String]> {
+		self.0.iter().map(|keys| keys.as_deref().unwrap_or_default())
+	}
+
+	/// Normalizes keys the same way their extractors normalize keys found in URLs.
+	pub fn normalize(&mut self) {
+		for (extractor, keys) in EXTRACTORS.iter().zip(&mut self.0) {
+			for key in keys.iter_mut().flatten() {
+				*key = extractor.normalize_key(key);
+			}
+		}
+	}
+
+	#[cfg(test)]
+	pub fn get(&self, config_key: &str) -> Option<&Vec<String>> {
+		let index = EXTRACTORS.iter().position(|extractor| extractor.config_key() == config_key)?;
+		self.0[index].as_ref()
+	}
+}
+
+impl Default for Sites {
+	fn default() -> Self {
+		Sites(vec![None; EXTRACTORS.len()])
+	}
+}
+
+impl<S: ErrorSpan> DecodePartial<S> for Sites {
+	fn insert_child(&mut self, node: &SpannedNode<S>, ctx: &mut Context<S>) -> Result<bool, DecodeError<S>> {
+		let name: &str = &node.node_name;
+		let index = match EXTRACTORS.iter().position(|extractor| extractor.config_key() == name) {
+			Some(index) => index,
+			None => return Ok(false),
+		};
+		if self.0[index].is_some() {
+			let message = format!("duplicate node `{}`, single node expected", name);
+			ctx.emit_error(DecodeError::unexpected(&node.node_name, "node", message));
+		}
+		let Keys(keys) = Keys::decode_node(node, ctx)?;
+		self.0[index] = Some(keys);
+		Ok(true)
+	}
+
+	fn insert_property(
+		&mut self,
+		_: &SpannedName<S>,
+		_: &Value<S>,
+		_: &mut Context<S>,
+	) -> Result<bool, DecodeError<S>> {
+		Ok(false)
+	}
+}
@@ -1,4 +1,4 @@
-// Silence the warning in the generated dbus client code.
-#[allow(clippy::bind_instead_of_map)]
+// Silence the warnings in the generated dbus client code.
+#[allow(clippy::bind_instead_of_map, dead_code)]
 pub mod client;
 pub mod server;
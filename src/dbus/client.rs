@@ -8,7 +8,7 @@ pub trait SolarUnneonVaxtify {
 	fn browser_register(&self, pid: u32) -> Result<(), dbus::Error>;
 	fn browser_unregister(&self, pid: u32) -> Result<(), dbus::Error>;
 	fn permit_end(&self, permit: &str) -> Result<(), dbus::Error>;
-	fn permit_start(&self, permit: &str) -> Result<(), dbus::Error>;
+	fn permit_start(&self, permit: &str, seconds: u64) -> Result<(), dbus::Error>;
 	fn service_reload(&self) -> Result<(), dbus::Error>;
 	fn tab_delete(&self, pid: u32, tab: i32) -> Result<(), dbus::Error>;
 	fn tab_update(&self, pid: u32, tab: i32, url: &str) -> Result<(), dbus::Error>;
@@ -27,8 +27,8 @@ impl<'a, T: blocking::BlockingSender, C: ::std::ops::Deref<Target = T>> SolarUnn
 		self.method_call("solar.unneon.Vaxtify", "PermitEnd", (permit,))
 	}
 
-	fn permit_start(&self, permit: &str) -> Result<(), dbus::Error> {
-		self.method_call("solar.unneon.Vaxtify", "PermitStart", (permit,))
+	fn permit_start(&self, permit: &str, seconds: u64) -> Result<(), dbus::Error> {
+		self.method_call("solar.unneon.Vaxtify", "PermitStart", (permit, seconds))
 	}
 
 	fn service_reload(&self) -> Result<(), dbus::Error> {
@@ -48,8 +48,11 @@ impl DBus {
 			let iface = Interface::new("solar.unneon.Vaxtify").unwrap();
 			let info = build_tree(tx);
 			let conn = LocalConnection::new_session().unwrap();
-			let name_reply = conn.request_name("solar.unneon.Vaxtify", false, false, true).unwrap();
-			assert_eq!(name_reply, RequestNameReply::PrimaryOwner);
+			let name_reply = conn.request_name(crate::paths::bus_name(), false, false, true).unwrap();
+			if name_reply != RequestNameReply::PrimaryOwner {
+				log::error!("D-Bus name {} is already taken, is another daemon running?", crate::paths::bus_name());
+				std::process::exit(1);
+			}
 			info.tree.start_receive(&conn);
 			loop {
 				conn.process(Duration::from_millis(100)).unwrap();
@@ -98,12 +101,15 @@ fn build_tree(event_tx: mpsc::Sender<Event>) -> TreeInfo {
 				}))
 				.add_m(
 					f.method("PermitStart", (), |m| {
-						let name = m.msg.read1()?;
+						// Length of zero seconds means the default length of the permit.
+						let (name, seconds): (_, u64) = m.msg.read2()?;
+						let length = Some(Duration::from_secs(seconds)).filter(|length| !length.is_zero());
 						let (err_tx, err_rx) = mpsc::sync_channel(0);
-						let event = Event::PermitRequest { name, err_tx };
+						let event = Event::PermitRequest { name, length, err_tx };
 						dbus_wait(m, event, err_rx)
 					})
-					.inarg::<&str, _>("permit"),
+					.inarg::<&str, _>("permit")
+					.inarg::<u64, _>("seconds"),
 				)
 				.add_m(
 					f.method("PermitEnd", (), |m| {
@@ -0,0 +1,202 @@
+use crate::config::{Config, Matchers};
+use crate::filters::EXTRACTORS;
+use std::str::FromStr;
+
+/// Format of block lists used by other tools.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+	/// Hosts file redirecting blocked domains to 0.0.0.0, which can only block whole domains.
+	Hosts,
+	/// uBlock Origin static filter list, using the Adblock syntax with regex filters.
+	Ublock,
+	/// LeechBlock NG site list, where each entry also matches subdomains and `+` marks exceptions.
+	Leechblock,
+}
+
+/// Block list in one of the other formats, along with warnings about matchers that could not be translated.
+#[derive(Debug, Default)]
+pub struct Export {
+	pub lines: Vec<String>,
+	pub warnings: Vec<String>,
+}
+
+/// Translates the given categories and all categories they include into the given format. Exceptions are translated
+/// when the format supports them, but apply to the entire list instead of their own category.
+pub fn export(config: &Config, format: Format, categories: &[String]) -> Result<Export, String> {
+	let mut export = Export::default();
+	for name in selected_categories(config, categories)? {
+		let category = config.categories.iter().find(|category| category.name == name).unwrap();
+		export.matchers(format, &category.name, &category.matchers, false);
+		if let Some(except) = &category.except {
+			export.matchers(format, &category.name, except, true);
+		}
+	}
+	Ok(export)
+}
+
+/// Resolves category names, adding categories included directly or indirectly.
+fn selected_categories<'a>(config: &'a Config, categories: &'a [String]) -> Result<Vec<&'a str>, String> {
+	let mut selected: Vec<&str> = Vec::new();
+	let mut stack = categories.iter().map(String::as_str).collect::<Vec<_>>();
+	while let Some(name) = stack.pop() {
+		let category = match config.categories.iter().find(|category| category.name == name) {
+			Some(category) => category,
+			None => return Err(format!("category {:?} does not exist", name)),
+		};
+		if !selected.contains(&name) {
+			selected.push(name);
+			stack.extend(category.includes.iter().flatten().map(String::as_str));
+		}
+	}
+	selected.sort_unstable_by_key(|name| config.categories.iter().position(|category| category.name == *name));
+	Ok(selected)
+}
+
+impl Export {
+	fn matchers(&mut self, format: Format, category: &str, matchers: &Matchers, is_except: bool) {
+		let files = matchers.domains_files.0.iter().flat_map(|file| &file.domains);
+		for domain in matchers.domains.iter().flatten().chain(files) {
+			let line = match (format, domain.strip_prefix("*."), domain.strip_prefix('.')) {
+				(Format::Hosts, None, None) => Some(format!("0.0.0.0 {}", domain)),
+				(Format::Hosts, _, _) => None,
+				(Format::Ublock, Some(parent), _) => Some(format!("|http*://*.{}^", parent)),
+				(Format::Ublock, None, Some(parent)) => Some(format!("||{}^", parent)),
+				(Format::Ublock, None, None) => Some(format!("|http*://{}^", domain)),
+				(Format::Leechblock, _, Some(parent)) => Some(parent.to_owned()),
+				(Format::Leechblock, _, None) => Some(domain.clone()),
+			};
+			self.push(format, category, is_except, "domain", domain, line);
+		}
+		for (extractor, keys) in EXTRACTORS.iter().zip(matchers.sites.iter()) {
+			for key in keys {
+				let path = match extractor.config_key() {
+					"subreddits" => Some(format!("reddit.com/r/{}", key)),
+					"githubs" if key.contains('/') => Some(format!("github.com/{}", key)),
+					"githubs" => Some(format!("gist.github.com/{}", key)),
+					_ => None,
+				};
+				let line = match (format, path) {
+					(Format::Ublock, Some(path)) => Some(format!("||{}^", path)),
+					(Format::Leechblock, Some(path)) => Some(path),
+					_ => None,
+				};
+				self.push(format, category, is_except, extractor.config_key(), key, line);
+			}
+		}
+		for prefix in matchers.path_prefixes.iter().flatten() {
+			let domain = prefix.domain.as_deref().unwrap_or("*");
+			let separator = if prefix.prefix.ends_with('/') { "" } else { "^" };
+			let line = match format {
+				Format::Hosts => None,
+				Format::Ublock => Some(format!("|http*://{}{}{}", domain, prefix.prefix, separator)),
+				Format::Leechblock => Some(format!("{}{}", domain, prefix.prefix)),
+			};
+			self.push(format, category, is_except, "path prefix", &format!("{}{}", domain, prefix.prefix), line);
+		}
+		for param in matchers.query_params.iter().flatten() {
+			self.push(format, category, is_except, "query parameter", &param.name, None);
+		}
+		for keyword in matchers.search_keywords.iter().flatten() {
+			self.push(format, category, is_except, "search keyword", keyword, None);
+		}
+		for regex in matchers.regexes.iter().flatten() {
+			// JavaScript regexes do not support inline flags, and everything else commonly used behaves the same.
+			let line = match format {
+				Format::Ublock if !regex.contains("(?") => Some(format!("/{}/", regex)),
+				_ => None,
+			};
+			self.push(format, category, is_except, "regex", regex, line);
+		}
+	}
+
+	fn push(&mut self, format: Format, category: &str, is_except: bool, kind: &str, value: &str, line: Option<String>) {
+		match line {
+			Some(line) if !is_except => self.lines.push(line),
+			Some(line) if format == Format::Ublock => self.lines.push(format!("@@{}", line)),
+			Some(line) if format == Format::Leechblock => self.lines.push(format!("+{}", line)),
+			_ => {
+				let kind = if is_except { format!("{} exception", kind) } else { kind.to_owned() };
+				self.warnings
+					.push(format!("{} {:?} in category {:?} can't be exported to {}", kind, value, category, format));
+			}
+		}
+	}
+}
+
+impl FromStr for Format {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"hosts" => Ok(Format::Hosts),
+			"ublock" => Ok(Format::Ublock),
+			"leechblock" => Ok(Format::Leechblock),
+			_ => Err(format!("invalid export format {:?}", s)),
+		}
+	}
+}
+
+impl std::fmt::Display for Format {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Format::Hosts => "hosts",
+			Format::Ublock => "ublock",
+			Format::Leechblock => "leechblock",
+		})
+	}
+}
+
+#[cfg(test)]
+const TEST_CONFIG: &str = r#"
+category "leisure" {
+	includes "reddit"
+	domains "www.youtube.com" ".twitch.tv"
+	regexes r"\w+\+memes"
+}
+
+category "reddit" {
+	domains "www.reddit.com"
+	subreddits "funny"
+	except {
+		subreddits "rust"
+	}
+}
+
+category "other" {
+	domains "example.com"
+}
+"#;
+
+#[test]
+fn hosts() {
+	let config = Config::parse(TEST_CONFIG).unwrap();
+	let list = export(&config, Format::Hosts, &["leisure".to_owned()]).unwrap();
+	assert_eq!(list.lines, ["0.0.0.0 www.youtube.com", "0.0.0.0 www.reddit.com"]);
+	assert_eq!(list.warnings.len(), 4);
+}
+
+#[test]
+fn ublock() {
+	let config = Config::parse(TEST_CONFIG).unwrap();
+	let list = export(&config, Format::Ublock, &["leisure".to_owned()]).unwrap();
+	assert_eq!(
+		list.lines,
+		[
+			"|http*://www.youtube.com^",
+			"||twitch.tv^",
+			r"/\w+\+memes/",
+			"|http*://www.reddit.com^",
+			"||reddit.com/r/funny^",
+			"@@||reddit.com/r/rust^",
+		]
+	);
+	assert!(list.warnings.is_empty());
+}
+
+#[test]
+fn leechblock() {
+	let config = Config::parse(TEST_CONFIG).unwrap();
+	let list = export(&config, Format::Leechblock, &["reddit".to_owned()]).unwrap();
+	assert_eq!(list.lines, ["www.reddit.com", "reddit.com/r/funny", "+reddit.com/r/rust"]);
+	assert!(export(&config, Format::Leechblock, &["missing".to_owned()]).is_err());
+}
@@ -1,35 +1,449 @@
-use url::Url;
+use url::{Host, Position, Url};
 
-pub fn extract_subreddit(url: &Url) -> Option<String> {
-	if url.domain()? != "www.reddit.com" {
-		return None;
+/// Normalizes a domain the same way browsers do, lowercasing it, encoding international names with punycode and
+/// removing the trailing dot of fully qualified names.
+pub fn normalize_domain(domain: &str) -> Option<String> {
+	let domain = domain.strip_suffix('.').unwrap_or(domain);
+	match Host::parse(domain).ok()? {
+		Host::Domain(domain) => Some(domain),
+		ip => Some(ip.to_string()),
 	}
-	let path_segments = url.path_segments()?.collect::<Vec<_>>();
-	match path_segments.as_slice() {
-		["r", subreddit, ..] => Some(subreddit.to_lowercase()),
-		_ => None,
+}
+
+/// Checks whether the path starts with the given prefix, treating it as a list of whole path segments.
+pub fn path_has_prefix(path: &str, prefix: &str) -> bool {
+	match path.strip_prefix(prefix) {
+		Some(rest) => prefix.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+		None => false,
 	}
 }
 
-pub fn extract_github(url: &Url) -> Option<String> {
-	if url.domain()? != "github.com" {
-		return None;
+/// Unwraps URLs of pages showing some other page, such as view-source, reader mode, translation proxies, AMP caches and
+/// web archives, returning the URL of the page shown.
+pub fn unwrap_url(url: &Url) -> Option<Url> {
+	match url.scheme() {
+		"view-source" => return parse_embedded_url(&url.as_str()["view-source:".len()..]),
+		"about" if url.path() == "reader" => return parse_embedded_url(&query_param(url, "url")?),
+		"http" | "https" => (),
+		_ => return None,
 	}
-	let path_segments = url.path_segments()?.collect::<Vec<_>>();
-	match path_segments.as_slice() {
-		[user, repo, ..] => Some(format!("{}/{}", user, repo)),
+	let domain = url.domain()?;
+	let rest = &url[Position::BeforePath..];
+	let labels = domain.split('.').collect::<Vec<_>>();
+	match labels.as_slice() {
+		[proxied, "translate", "goog"] => {
+			let mut unwrapped = url.clone();
+			unwrapped.set_host(Some(&decode_proxied_host(proxied))).ok()?;
+			let query = url.query_pairs().filter(|(name, _)| !name.starts_with("_x_tr_")).collect::<Vec<_>>();
+			unwrapped.query_pairs_mut().clear().extend_pairs(query);
+			if unwrapped.query() == Some("") {
+				unwrapped.set_query(None);
+			}
+			Some(unwrapped)
+		}
+		["translate", "google", ..] => parse_embedded_url(&query_param(url, "u")?),
+		[_, "cdn", "ampproject", "org"] => {
+			let rest =
+				rest.strip_prefix("/c/").or_else(|| rest.strip_prefix("/v/")).or_else(|| rest.strip_prefix("/i/"))?;
+			parse_amp_url(rest)
+		}
+		["www", "google", ..] | ["google", ..] => parse_amp_url(rest.strip_prefix("/amp/")?),
+		["web", "archive", "org"] => {
+			let rest = rest.strip_prefix("/web/")?;
+			let (_timestamp, target) = rest.split_once('/')?;
+			parse_embedded_url(target)
+		}
+		["archive", "ph" | "is" | "today" | "li" | "vn" | "md" | "fo"] => {
+			let (_timestamp, target) = rest[1..].split_once('/')?;
+			parse_embedded_url(target)
+		}
 		_ => None,
 	}
 }
 
+/// Parses a URL embedded in a path or a query parameter, which often omits the scheme.
+fn parse_embedded_url(text: &str) -> Option<Url> {
+	if text.starts_with("http://") || text.starts_with("https://") {
+		Url::parse(text).ok()
+	} else if text.contains('.') {
+		Url::parse(&format!("http://{}", text)).ok()
+	} else {
+		None
+	}
+}
+
+/// Parses a URL embedded by AMP caches, with `s/` in front marking an HTTPS one.
+fn parse_amp_url(rest: &str) -> Option<Url> {
+	match rest.strip_prefix("s/") {
+		Some(rest) => Url::parse(&format!("https://{}", rest)).ok(),
+		None => Url::parse(&format!("http://{}", rest)).ok(),
+	}
+}
+
+/// Decodes a host from a single label, with dots written as dashes and dashes written as double dashes.
+fn decode_proxied_host(label: &str) -> String {
+	label.split("--").map(|part| part.replace('-', ".")).collect::<Vec<_>>().join("-")
+}
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+	url.query_pairs().find(|(param, _)| param == name).map(|(_, value)| value.into_owned())
+}
+
+/// Extracts the query from a search engine result page, lowercased and with URL encoding removed.
+pub fn extract_search_query(url: &Url) -> Option<String> {
+	let domain = url.domain()?;
+	let param = match domain.split('.').collect::<Vec<_>>().as_slice() {
+		["www", "google", ..] | ["google", ..] if url.path() == "/search" => "q",
+		["duckduckgo", "com"] | ["html", "duckduckgo", "com"] | ["lite", "duckduckgo", "com"] => "q",
+		["www", "bing", "com"] if url.path() == "/search" => "q",
+		["www", "startpage", "com"] | ["startpage", "com"] => "query",
+		["kagi", "com"] if url.path() == "/search" => "q",
+		_ => return None,
+	};
+	Some(query_param(url, param)?.to_lowercase())
+}
+
+/// Extracts a site-specific key from URLs, such as a subreddit name, which categories can then match exactly.
+pub trait Extractor: Sync {
+	/// Name of the category node listing the keys, such as `subreddits`.
+	fn config_key(&self) -> &'static str;
+
+	fn extract(&self, url: &Url) -> Option<String>;
+
+	/// Normalizes a key written in the config the same way keys extracted from URLs are.
+	fn normalize_key(&self, key: &str) -> String {
+		key.to_owned()
+	}
+}
+
+/// All site extractors, each with its own config key and index in the lookups.
+pub static EXTRACTORS: &[&dyn Extractor] = &[
+	&Subreddit,
+	&Github,
+	&YoutubeChannel,
+	&TwitchChannel,
+	&TwitterAccount,
+	&MastodonAccount,
+	&HackerNews,
+	&StackExchange,
+];
+
+pub struct Subreddit;
+pub struct Github;
+pub struct YoutubeChannel;
+pub struct TwitchChannel;
+pub struct TwitterAccount;
+pub struct MastodonAccount;
+pub struct HackerNews;
+pub struct StackExchange;
+
+impl Extractor for Subreddit {
+	fn config_key(&self) -> &'static str {
+		"subreddits"
+	}
+
+	fn extract(&self, url: &Url) -> Option<String> {
+		let domain = url.domain()?;
+		if domain != "reddit.com" && !domain.ends_with(".reddit.com") {
+			return None;
+		}
+		let path_segments = url.path_segments()?.collect::<Vec<_>>();
+		match path_segments.as_slice() {
+			["r", subreddit, ..] => Some(subreddit.to_lowercase()),
+			_ => None,
+		}
+	}
+
+	fn normalize_key(&self, key: &str) -> String {
+		key.to_lowercase()
+	}
+}
+
+/// Matches repositories as `user/repo`, including their raw files and GitHub Pages sites. Gists are matched by the
+/// name of the user who created them.
+impl Extractor for Github {
+	fn config_key(&self) -> &'static str {
+		"githubs"
+	}
+
+	fn extract(&self, url: &Url) -> Option<String> {
+		let domain = url.domain()?;
+		let path_segments = url.path_segments()?.filter(|segment| !segment.is_empty()).collect::<Vec<_>>();
+		match (domain, path_segments.as_slice()) {
+			("github.com" | "www.github.com" | "raw.githubusercontent.com", [user, repo, ..]) => {
+				Some(format!("{}/{}", user, repo))
+			}
+			("gist.github.com", [user, ..]) => Some(user.to_string()),
+			(domain, path_segments) => {
+				let user = domain.strip_suffix(".github.io")?;
+				match path_segments {
+					[repo, ..] => Some(format!("{}/{}", user, repo)),
+					[] => Some(format!("{}/{}", user, domain)),
+				}
+			}
+		}
+	}
+}
+
+/// Matches channel pages by their handle, like `@veritasium`, or by their ID. Videos do not say which channel they
+/// belong to in the URL, so they are not matched.
+impl Extractor for YoutubeChannel {
+	fn config_key(&self) -> &'static str {
+		"youtube-channels"
+	}
+
+	fn extract(&self, url: &Url) -> Option<String> {
+		if !matches!(url.domain()?, "www.youtube.com" | "m.youtube.com" | "youtube.com") {
+			return None;
+		}
+		let path_segments = url.path_segments()?.collect::<Vec<_>>();
+		match path_segments.as_slice() {
+			[handle, ..] if handle.starts_with('@') => Some(handle.to_lowercase()),
+			["channel", id, ..] => Some(id.to_string()),
+			_ => None,
+		}
+	}
+
+	fn normalize_key(&self, key: &str) -> String {
+		// Channel IDs are case-sensitive, unlike handles.
+		if key.starts_with('@') {
+			key.to_lowercase()
+		} else {
+			key.to_owned()
+		}
+	}
+}
+
+impl Extractor for TwitchChannel {
+	fn config_key(&self) -> &'static str {
+		"twitch-channels"
+	}
+
+	fn extract(&self, url: &Url) -> Option<String> {
+		if !matches!(url.domain()?, "www.twitch.tv" | "m.twitch.tv" | "twitch.tv") {
+			return None;
+		}
+		let path_segments = url.path_segments()?.collect::<Vec<_>>();
+		match path_segments.as_slice() {
+			[channel, ..] if !channel.is_empty() => Some(channel.to_lowercase()),
+			_ => None,
+		}
+	}
+
+	fn normalize_key(&self, key: &str) -> String {
+		key.to_lowercase()
+	}
+}
+
+impl Extractor for TwitterAccount {
+	fn config_key(&self) -> &'static str {
+		"twitter-accounts"
+	}
+
+	fn extract(&self, url: &Url) -> Option<String> {
+		let domain = url.domain()?;
+		let domain = domain.strip_prefix("www.").or_else(|| domain.strip_prefix("mobile.")).unwrap_or(domain);
+		if !matches!(domain, "twitter.com" | "x.com") {
+			return None;
+		}
+		let path_segments = url.path_segments()?.collect::<Vec<_>>();
+		match path_segments.as_slice() {
+			[account, ..] if !account.is_empty() => Some(account.to_lowercase()),
+			_ => None,
+		}
+	}
+
+	fn normalize_key(&self, key: &str) -> String {
+		key.to_lowercase()
+	}
+}
+
+/// Matches profiles and posts on any instance, with keys written as `user@mastodon.social`.
+impl Extractor for MastodonAccount {
+	fn config_key(&self) -> &'static str {
+		"mastodon-accounts"
+	}
+
+	fn extract(&self, url: &Url) -> Option<String> {
+		let domain = url.domain()?;
+		let path_segments = url.path_segments()?.collect::<Vec<_>>();
+		match path_segments.as_slice() {
+			[account, ..] if account.len() > 1 && account.starts_with('@') => {
+				Some(format!("{}@{}", account[1..].to_lowercase(), domain))
+			}
+			_ => None,
+		}
+	}
+
+	fn normalize_key(&self, key: &str) -> String {
+		key.to_lowercase()
+	}
+}
+
+/// Matches Hacker News pages by their name, like `newest` or `item`, with the front page called `news`.
+impl Extractor for HackerNews {
+	fn config_key(&self) -> &'static str {
+		"hackernews-pages"
+	}
+
+	fn extract(&self, url: &Url) -> Option<String> {
+		if url.domain()? != "news.ycombinator.com" {
+			return None;
+		}
+		match url.path() {
+			"/" => Some("news".to_owned()),
+			path => Some(path[1..].to_owned()),
+		}
+	}
+}
+
+/// Matches Stack Exchange sites by their name, like `stackoverflow` or `math` for math.stackexchange.com.
+impl Extractor for StackExchange {
+	fn config_key(&self) -> &'static str {
+		"stackexchange-sites"
+	}
+
+	fn extract(&self, url: &Url) -> Option<String> {
+		let domain = url.domain()?;
+		let domain = domain.strip_prefix("www.").unwrap_or(domain);
+		if let Some(site) = domain.strip_suffix(".stackexchange.com") {
+			return Some(site.to_owned());
+		}
+		match domain {
+			"stackoverflow.com" | "superuser.com" | "serverfault.com" | "askubuntu.com" | "stackapps.com" => {
+				Some(domain.strip_suffix(".com").unwrap().to_owned())
+			}
+			"mathoverflow.net" => Some("mathoverflow".to_owned()),
+			_ => None,
+		}
+	}
+}
+
 #[test]
-fn reddit_lowercase() {
-	let url = "https://www.reddit.com/r/PrOgRaMmInG/".parse().unwrap();
-	assert_eq!(extract_subreddit(&url).as_deref(), Some("programming"));
+fn domain_normalization() {
+	assert_eq!(normalize_domain("WWW.YouTube.com.").as_deref(), Some("www.youtube.com"));
+	assert_eq!(normalize_domain("bücher.de").as_deref(), Some("xn--bcher-kva.de"));
+	assert_eq!(normalize_domain("not a domain"), None);
+}
+
+#[test]
+fn path_prefix() {
+	assert!(path_has_prefix("/shorts", "/shorts"));
+	assert!(path_has_prefix("/shorts/abc", "/shorts"));
+	assert!(!path_has_prefix("/shortstop", "/shorts"));
+	assert!(path_has_prefix("/feed/trending", "/feed/"));
+}
+
+#[test]
+fn reddit() {
+	let urls = [
+		"https://www.reddit.com/r/programming/",
+		"https://reddit.com/r/programming",
+		"https://old.reddit.com/r/programming/comments/abc/title/",
+		"https://new.reddit.com/r/programming/top/?t=week",
+		"https://np.reddit.com/r/programming/",
+		"https://i.reddit.com/r/programming",
+		"https://m.reddit.com/r/programming",
+		"https://www.reddit.com/r/PrOgRaMmInG/",
+	];
+	for url in urls.iter() {
+		assert_eq!(Subreddit.extract(&url.parse().unwrap()).as_deref(), Some("programming"), "{}", url);
+	}
+	assert_eq!(Subreddit.extract(&"https://www.reddit.com/user/spez".parse().unwrap()), None);
+	assert_eq!(Subreddit.extract(&"https://notreddit.com/r/programming".parse().unwrap()), None);
+}
+
+#[test]
+fn unwrapping() {
+	let cases = [
+		("view-source:https://www.reddit.com/r/funny/", "https://www.reddit.com/r/funny/"),
+		("about:reader?url=https%3A%2F%2Fwww.reddit.com%2Fr%2Ffunny%2F", "https://www.reddit.com/r/funny/"),
+		("https://www-reddit-com.translate.goog/r/funny/?_x_tr_sl=auto&_x_tr_tl=en", "https://www.reddit.com/r/funny/"),
+		("https://my--site-example-com.translate.goog/?a=b&_x_tr_sl=auto", "https://my-site.example.com/?a=b"),
+		(
+			"https://translate.google.com/translate?sl=auto&u=https://www.reddit.com/r/funny/",
+			"https://www.reddit.com/r/funny/",
+		),
+		("https://www-reddit-com.cdn.ampproject.org/c/s/www.reddit.com/r/funny/", "https://www.reddit.com/r/funny/"),
+		("https://www.google.com/amp/s/www.reddit.com/r/funny/", "https://www.reddit.com/r/funny/"),
+		(
+			"https://web.archive.org/web/20210101000000/https://www.reddit.com/r/funny/",
+			"https://www.reddit.com/r/funny/",
+		),
+		(
+			"https://web.archive.org/web/2021id_/www.reddit.com/r/funny/?sort=new",
+			"http://www.reddit.com/r/funny/?sort=new",
+		),
+		("https://archive.ph/newest/https://www.reddit.com/r/funny/", "https://www.reddit.com/r/funny/"),
+	];
+	for (url, unwrapped) in cases.iter() {
+		assert_eq!(unwrap_url(&url.parse().unwrap()).as_ref().map(Url::as_str), Some(*unwrapped), "{}", url);
+	}
+	assert_eq!(unwrap_url(&"https://www.reddit.com/r/funny/".parse().unwrap()), None);
+	assert_eq!(unwrap_url(&"https://archive.ph/AbCdE".parse().unwrap()), None);
+	assert_eq!(unwrap_url(&"https://www.google.com/search?q=cat+memes".parse().unwrap()), None);
+}
+
+#[test]
+fn search_engines() {
+	let urls = [
+		"https://www.google.com/search?q=Cat+Memes&client=firefox-b-d",
+		"https://www.google.co.uk/search?q=cat%20memes",
+		"https://duckduckgo.com/?q=cat+memes&ia=web",
+		"https://html.duckduckgo.com/html/?q=cat+memes",
+		"https://www.bing.com/search?q=cat+memes&form=QBLH",
+		"https://www.startpage.com/sp/search?query=cat+memes",
+		"https://kagi.com/search?q=cat+memes",
+	];
+	for url in urls.iter() {
+		assert_eq!(extract_search_query(&url.parse().unwrap()).as_deref(), Some("cat memes"), "{}", url);
+	}
+	assert_eq!(extract_search_query(&"https://www.google.com/maps?q=cat+memes".parse().unwrap()), None);
 }
 
 #[test]
 fn github() {
-	let url = "https://github.com/unneon/icie".parse().unwrap();
-	assert_eq!(extract_github(&url).as_deref(), Some("unneon/icie"));
+	let cases = [
+		("https://github.com/unneon/icie", "unneon/icie"),
+		("https://github.com/unneon/icie/issues/1", "unneon/icie"),
+		("https://www.github.com/unneon/icie", "unneon/icie"),
+		("https://raw.githubusercontent.com/unneon/icie/master/README.md", "unneon/icie"),
+		("https://gist.github.com/unneon/0123456789abcdef", "unneon"),
+		("https://unneon.github.io/icie/", "unneon/icie"),
+		("https://unneon.github.io/", "unneon/unneon.github.io"),
+	];
+	for (url, key) in cases.iter() {
+		assert_eq!(Github.extract(&url.parse().unwrap()).as_deref(), Some(*key), "{}", url);
+	}
+	assert_eq!(Github.extract(&"https://github.com/unneon".parse().unwrap()), None);
+	assert_eq!(Github.extract(&"https://docs.github.com/en".parse().unwrap()), None);
+}
+
+#[test]
+fn sites() {
+	let cases: [(&dyn Extractor, &str, &str); 10] = [
+		(&YoutubeChannel, "https://www.youtube.com/@Veritasium/videos", "@veritasium"),
+		(&YoutubeChannel, "https://m.youtube.com/channel/UCHnyfMqiRRG1u-2MsSQLbXA", "UCHnyfMqiRRG1u-2MsSQLbXA"),
+		(&TwitchChannel, "https://www.twitch.tv/Gamesdonequick", "gamesdonequick"),
+		(&TwitterAccount, "https://x.com/rustlang/status/1", "rustlang"),
+		(&TwitterAccount, "https://mobile.twitter.com/RustLang", "rustlang"),
+		(&MastodonAccount, "https://hachyderm.io/@Unneon/1234", "unneon@hachyderm.io"),
+		(&HackerNews, "https://news.ycombinator.com/", "news"),
+		(&HackerNews, "https://news.ycombinator.com/item?id=1", "item"),
+		(&StackExchange, "https://stackoverflow.com/questions/1", "stackoverflow"),
+		(&StackExchange, "https://math.stackexchange.com/questions/1", "math"),
+	];
+	for (extractor, url, key) in cases.iter() {
+		assert_eq!(extractor.extract(&url.parse().unwrap()).as_deref(), Some(*key), "{}", url);
+	}
+	assert_eq!(YoutubeChannel.extract(&"https://www.youtube.com/watch?v=abc".parse().unwrap()), None);
+	assert_eq!(TwitchChannel.extract(&"https://www.twitch.com/gamesdonequick".parse().unwrap()), None);
+}
+
+#[test]
+fn unique_config_keys() {
+	let mut keys = EXTRACTORS.iter().map(|extractor| extractor.config_key()).collect::<Vec<_>>();
+	keys.sort_unstable();
+	keys.dedup();
+	assert_eq!(keys.len(), EXTRACTORS.len());
 }
@@ -0,0 +1,286 @@
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Config converted from another tool, along with warnings about settings that could not be represented.
+#[derive(Debug, Default)]
+pub struct Import {
+	pub config: String,
+	pub warnings: Vec<String>,
+}
+
+/// Converts a LeechBlock NG options export into a category and a rule for each block set, and a permit for block sets
+/// with time limits.
+pub fn import_leechblock(text: &str) -> Result<Import, String> {
+	let options: Value = serde_json::from_str(text).map_err(|e| format!("invalid LeechBlock export ({})", e))?;
+	let option = |name: &str, set: usize| options.get(format!("{}{}", name, set));
+	let set_count = options.get("numSets").and_then(number).unwrap_or(6);
+	let mut import = Import::default();
+	let set_name =
+		|set: usize| option("setName", set).and_then(Value::as_str).map(str::trim).filter(|name| !name.is_empty());
+	let set_names = (1..=set_count as usize).filter_map(set_name).collect::<HashSet<_>>();
+	let mut names = HashSet::new();
+	for set in 1..=set_count as usize {
+		let sites = option("sites", set).and_then(Value::as_str).unwrap_or_default();
+		let regex_block = option("regexpBlock", set).and_then(Value::as_str).unwrap_or_default();
+		if sites.trim().is_empty() && regex_block.is_empty() {
+			continue;
+		}
+		let name = match set_name(set) {
+			Some(name) if !names.contains(name) => name.to_owned(),
+			// Fallback names must not collide with names of other sets, including ones that come later.
+			_ => (1..)
+				.map(|n| if n == 1 { format!("set{}", set) } else { format!("set{}-{}", set, n) })
+				.find(|name| !names.contains(name) && !set_names.contains(name.as_str()))
+				.unwrap(),
+		};
+		names.insert(name.clone());
+		import.category(&name, sites, regex_block, option("regexpAllow", set).and_then(Value::as_str));
+		let times = option("times", set).and_then(Value::as_str).unwrap_or_default();
+		let days = option("days", set).and_then(Value::as_array);
+		import.rule(&name, times, days)?;
+		let limit = option("limitMins", set).and_then(number).filter(|limit| *limit > 0);
+		if let Some(limit) = limit {
+			let period = option("limitPeriod", set).and_then(number).unwrap_or(3600);
+			import.permit(&name, limit, period);
+		}
+		if option("conjMode", set).and_then(Value::as_bool).unwrap_or(false) {
+			import.warn(&name, "blocking only when both time periods and time limits apply is not supported");
+		}
+	}
+	Ok(import)
+}
+
+impl Import {
+	fn category(&mut self, name: &str, sites: &str, regex_block: &str, regex_allow: Option<&str>) {
+		let mut matchers = Matchers::default();
+		let mut except = Matchers::default();
+		for site in sites.split_whitespace() {
+			let (matchers, site) = match site.strip_prefix('+') {
+				Some(site) => (&mut except, site),
+				None => (&mut matchers, site),
+			};
+			if site.is_empty() || site.starts_with('>') || site.strip_prefix("*.").unwrap_or(site).contains('*') {
+				self.warn(name, &format!("site {:?} is not supported", site));
+				continue;
+			}
+			let site = site.strip_prefix("https://").or_else(|| site.strip_prefix("http://")).unwrap_or(site);
+			let site = site.strip_prefix("www.").unwrap_or(site);
+			match site.split_once('/') {
+				// LeechBlock matches subdomains too, which Vaxtify only does for patterns starting with a dot.
+				None if site.starts_with("*.") => matchers.domains.push(site.to_owned()),
+				None => matchers.domains.push(format!(".{}", site)),
+				Some((domain, _)) if domain.contains('*') => {
+					self.warn(name, &format!("site {:?} is not supported", site));
+				}
+				// Addresses with paths only match the given domain, and its www subdomain for the most common case.
+				Some((domain, path)) => {
+					matchers.path_prefixes.push(format!("{}/{}", domain, path));
+					if domain.split('.').count() == 2 {
+						matchers.path_prefixes.push(format!("www.{}/{}", domain, path));
+					}
+				}
+			}
+		}
+		for (matchers, regex) in
+			IntoIterator::into_iter([(&mut matchers, Some(regex_block)), (&mut except, regex_allow)])
+		{
+			if let Some(regex) = regex.filter(|regex| !regex.is_empty()) {
+				if regex::Regex::new(regex).is_ok() {
+					matchers.regexes.push(regex.to_owned());
+				} else {
+					self.warn(name, &format!("regex {:?} is not supported", regex));
+				}
+			}
+		}
+		writeln!(self.config, "category {:?} {{", name).unwrap();
+		matchers.write(&mut self.config, "\t");
+		if !except.is_empty() {
+			self.config.push_str("\texcept {\n");
+			except.write(&mut self.config, "\t\t");
+			self.config.push_str("\t}\n");
+		}
+		self.config.push_str("}\n\n");
+	}
+
+	fn rule(&mut self, name: &str, times: &str, days: Option<&Vec<Value>>) -> Result<(), String> {
+		let blocked_days = match days {
+			Some(days) => days.iter().map(|day| day.as_bool().unwrap_or(false)).collect::<Vec<_>>(),
+			None => vec![true; 7],
+		};
+		let mut blocked_times = Vec::new();
+		for range in times.split(',').filter(|range| !range.trim().is_empty()) {
+			let (since, until) =
+				range.trim().split_once('-').ok_or_else(|| format!("invalid time range {:?}", range))?;
+			let (since, until) = (parse_time(since)?, parse_time(until)?);
+			if since <= until {
+				blocked_times.push((since, until));
+			} else {
+				// Ranges crossing midnight are split in two, both applying on the same blocked days.
+				let details =
+					format!("time range {:?} was split at midnight, with both parts on the same days", range.trim());
+				self.warn(name, &details);
+				blocked_times.push((since, 24 * 60));
+				blocked_times.push((0, until));
+			}
+		}
+		if blocked_times.is_empty() {
+			blocked_times.push((0, 24 * 60));
+		}
+		blocked_times.sort_unstable();
+		writeln!(self.config, "rule {:?} {{", name).unwrap();
+		let free_days = WEEKDAYS.iter().zip(&blocked_days).filter(|(_, blocked)| !**blocked).map(|(day, _)| *day);
+		let free_days = free_days.collect::<Vec<_>>();
+		if free_days.len() == WEEKDAYS.len() {
+			self.warn(name, "block set is not active on any day, so the rule is never active");
+		}
+		if !free_days.is_empty() {
+			writeln!(self.config, "\tallowed {{\n\t\tdays {}\n\t}}", quoted(&free_days)).unwrap();
+		}
+		let blocked_days = WEEKDAYS.iter().zip(&blocked_days).filter(|(_, blocked)| **blocked).map(|(day, _)| *day);
+		let blocked_days = blocked_days.collect::<Vec<_>>();
+		let mut free_since = 0;
+		for (since, until) in blocked_times.iter().copied().chain(std::iter::once((24 * 60, 24 * 60))) {
+			if since > free_since && !blocked_days.is_empty() {
+				self.config.push_str("\tallowed {\n");
+				writeln!(self.config, "\t\tsince {}", kdl_time(free_since)).unwrap();
+				if since < 24 * 60 {
+					writeln!(self.config, "\t\tuntil {}", kdl_time(since)).unwrap();
+				}
+				if blocked_days.len() < WEEKDAYS.len() {
+					writeln!(self.config, "\t\tdays {}", quoted(&blocked_days)).unwrap();
+				}
+				self.config.push_str("\t}\n");
+			}
+			free_since = free_since.max(until);
+		}
+		writeln!(self.config, "\tcategories {:?}\n}}\n", name).unwrap();
+		Ok(())
+	}
+
+	fn permit(&mut self, name: &str, limit: u64, period: u64) {
+		self.warn(name, "time limit was converted to a permit, which has to be started manually");
+		writeln!(self.config, "permit {:?} {{", name).unwrap();
+		writeln!(self.config, "\tlength mins={}", limit).unwrap();
+		writeln!(self.config, "\tcooldown seconds={}", period).unwrap();
+		writeln!(self.config, "\tcategories {:?}\n}}\n", name).unwrap();
+	}
+
+	fn warn(&mut self, name: &str, details: &str) {
+		self.warnings.push(format!("block set {:?}: {}", name, details));
+	}
+}
+
+#[derive(Default)]
+struct Matchers {
+	domains: Vec<String>,
+	path_prefixes: Vec<String>,
+	regexes: Vec<String>,
+}
+
+impl Matchers {
+	fn is_empty(&self) -> bool {
+		self.domains.is_empty() && self.path_prefixes.is_empty() && self.regexes.is_empty()
+	}
+
+	fn write(&self, config: &mut String, indent: &str) {
+		for (node, values) in [("domains", &self.domains), ("path-prefixes", &self.path_prefixes)].iter() {
+			if !values.is_empty() {
+				writeln!(config, "{}{} {}", indent, node, quoted(values)).unwrap();
+			}
+		}
+		if !self.regexes.is_empty() {
+			let regexes = self.regexes.iter().map(|regex| raw_string(regex)).collect::<Vec<_>>();
+			writeln!(config, "{}regexes {}", indent, regexes.join(" ")).unwrap();
+		}
+	}
+}
+
+/// Reads numbers, which LeechBlock sometimes stores as strings.
+fn number(value: &Value) -> Option<u64> {
+	value.as_u64().or_else(|| value.as_str()?.parse().ok())
+}
+
+/// Parses LeechBlock times like `0930`, returning minutes since midnight.
+fn parse_time(time: &str) -> Result<u32, String> {
+	let time = time.trim();
+	let minutes = match (time.get(..2).map(str::parse::<u32>), time.get(2..).map(str::parse::<u32>)) {
+		(Some(Ok(hour)), Some(Ok(min))) if time.len() == 4 && min < 60 => hour * 60 + min,
+		_ => return Err(format!("invalid time {:?}", time)),
+	};
+	if minutes > 24 * 60 {
+		return Err(format!("invalid time {:?}", time));
+	}
+	Ok(minutes)
+}
+
+fn kdl_time(minutes: u32) -> String {
+	match (minutes / 60 % 24, minutes % 60) {
+		(hour, 0) => format!("hour={}", hour),
+		(hour, min) => format!("hour={} min={}", hour, min),
+	}
+}
+
+/// Writes a KDL raw string, with enough `#`s that the text can't end it early.
+fn raw_string(text: &str) -> String {
+	let hashes = (0..).map(|count| "#".repeat(count)).find(|hashes| !text.contains(&format!("\"{}", hashes))).unwrap();
+	format!("r{0}\"{1}\"{0}", hashes, text)
+}
+
+fn quoted<T: AsRef<str>>(values: &[T]) -> String {
+	values.iter().map(|value| format!("{:?}", value.as_ref())).collect::<Vec<_>>().join(" ")
+}
+
+#[test]
+fn leechblock() {
+	let text = r#"{
+		"numSets": "2",
+		"setName1": "Social",
+		"sites1": "facebook.com +facebook.com/messages twitter.com >cats",
+		"times1": "0900-1200,1300-1730",
+		"days1": [false, true, true, true, true, true, false],
+		"limitMins1": "30",
+		"limitPeriod1": "86400",
+		"setName2": "",
+		"sites2": "",
+		"regexpBlock2": "^https?://(www\\.)?youtube\\.com/shorts",
+		"times2": ""
+	}"#;
+	let import = import_leechblock(text).unwrap();
+	let config = crate::config::Config::parse(&import.config).unwrap();
+	assert_eq!(config.categories.len(), 2);
+	assert_eq!(
+		config.categories[0].matchers.domains,
+		Some(vec![".facebook.com".to_owned(), ".twitter.com".to_owned()])
+	);
+	assert_eq!(config.categories[1].name, "set2");
+	assert_eq!(config.rules.len(), 2);
+	assert_eq!(config.rules[0].allowed.len(), 4);
+	assert_eq!(config.rules[1].allowed.len(), 0);
+	assert_eq!(config.permits.len(), 1);
+	assert_eq!(import.warnings.len(), 2);
+}
+
+#[test]
+fn leechblock_edge_cases() {
+	let text = r###"{
+		"numSets": "3",
+		"setName1": "",
+		"sites1": "reddit.com",
+		"times1": "2200-0200",
+		"setName2": "set1",
+		"sites2": "twitter.com",
+		"setName3": "Quotes",
+		"regexpBlock3": "\"#\"##"
+	}"###;
+	let import = import_leechblock(text).unwrap();
+	let config = crate::config::Config::parse(&import.config).unwrap();
+	let names = config.categories.iter().map(|category| category.name.as_str()).collect::<Vec<_>>();
+	assert_eq!(names, ["set1-2", "set1", "Quotes"]);
+	assert_eq!(config.categories[2].matchers.regexes, Some(vec!["\"#\"##".to_owned()]));
+	assert_eq!(config.rules[0].allowed.len(), 1);
+	assert_eq!(config.rules[0].allowed[0].to_string(), "since 02:00:00 until 22:00:00");
+	assert_eq!(import.warnings.len(), 1);
+}
@@ -0,0 +1,172 @@
+use crate::config::{Config, Matchers};
+use crate::filters::EXTRACTORS;
+use chrono::{DateTime, Local, TimeZone};
+use std::collections::HashSet;
+
+/// Finds likely mistakes in a valid config, such as blocks that never have any effect.
+pub fn lint(config: &Config) -> Vec<String> {
+	let mut warnings = Vec::new();
+	let week = sample_week();
+	let including = including_categories(config);
+	let related = related_categories(config);
+	for (index, category) in config.categories.iter().enumerate() {
+		let used = config.rules.iter().map(|rule| &rule.categories).chain(config.permits.iter().map(|p| &p.categories));
+		if !used.flatten().any(|name| including[index].contains(name.as_str())) {
+			warnings.push(format!("category {:?} is not used by any rule or permit", category.name));
+		}
+		duplicate_matchers(&category.name, &category.matchers, &mut warnings);
+		if let Some(except) = &category.except {
+			duplicate_matchers(&category.name, except, &mut warnings);
+		}
+	}
+	for rule in &config.rules {
+		if !week.iter().any(|now| rule.is_active(now)) {
+			warnings.push(format!("rule {:?} is never active", rule.name));
+		}
+	}
+	for permit in &config.permits {
+		let blocking = config.rules.iter().filter(|rule| {
+			rule.categories.iter().any(|rule_category| {
+				let index = config.categories.iter().position(|category| category.name == *rule_category).unwrap();
+				permit.categories.iter().any(|category| related[index].contains(category.as_str()))
+			})
+		});
+		let blocking = blocking.collect::<Vec<_>>();
+		if !week.iter().any(|now| permit.is_available(now) && blocking.iter().any(|rule| rule.is_active(now))) {
+			warnings.push(format!("permit {:?} is never available while a rule blocks its categories", permit.name));
+		}
+	}
+	warnings
+}
+
+/// Every minute of a week, which is enough to tell whether some time range ever applies.
+fn sample_week() -> Vec<DateTime<Local>> {
+	let monday = Local.ymd(2026, 1, 5).and_hms(0, 0, 0);
+	(0..7 * 24 * 60).map(|minute| monday + chrono::Duration::minutes(minute)).collect()
+}
+
+/// For each category, lists the categories that include it, directly or indirectly, including itself. Rules and
+/// permits using any of them use the category too.
+fn including_categories(config: &Config) -> Vec<HashSet<&str>> {
+	closures(config, |index| {
+		let name = &config.categories[index].name;
+		let including = config
+			.categories
+			.iter()
+			.enumerate()
+			.filter(|(_, category)| category.includes.iter().flatten().any(|include| include == name));
+		including.map(|(index, _)| index).collect()
+	})
+}
+
+/// For each category, lists the categories that include it or are included by it, directly or indirectly, including
+/// itself.
+fn related_categories(config: &Config) -> Vec<HashSet<&str>> {
+	let find = |name: &str| config.categories.iter().position(|category| category.name == name).unwrap();
+	let included =
+		closures(config, |index| config.categories[index].includes.iter().flatten().map(|name| find(name)).collect());
+	including_categories(config).into_iter().zip(included).map(|(including, included)| &including | &included).collect()
+}
+
+/// For each category, lists the names of categories reachable from it through the given edges, including itself.
+fn closures(config: &Config, edges: impl Fn(usize) -> Vec<usize>) -> Vec<HashSet<&str>> {
+	(0..config.categories.len())
+		.map(|start| {
+			let mut visited = HashSet::new();
+			let mut stack = vec![start];
+			while let Some(current) = stack.pop() {
+				if visited.insert(config.categories[current].name.as_str()) {
+					stack.extend(edges(current));
+				}
+			}
+			visited
+		})
+		.collect()
+}
+
+fn duplicate_matchers(category: &str, matchers: &Matchers, warnings: &mut Vec<String>) {
+	let lists = [
+		("domains", matchers.domains.as_deref()),
+		("regexes", matchers.regexes.as_deref()),
+		("search-keywords", matchers.search_keywords.as_deref()),
+	];
+	let sites = EXTRACTORS.iter().map(|extractor| extractor.config_key()).zip(matchers.sites.iter().map(Some));
+	for (node, values) in IntoIterator::into_iter(lists).chain(sites) {
+		let mut seen = HashSet::new();
+		for value in values.unwrap_or_default() {
+			if !seen.insert(value) {
+				warnings.push(format!("{} {:?} in category {:?} is listed more than once", node, value, category));
+			}
+		}
+	}
+}
+
+#[test]
+fn warnings() {
+	let text = r#"
+category "social" {
+	domains "twitter.com" "twitter.com"
+}
+
+category "leisure" {
+	includes "social"
+}
+
+category "unused" {
+	domains "example.com"
+}
+
+category "videos" {
+	domains "www.youtube.com"
+}
+
+rule "never" {
+	allowed {
+	}
+	categories "leisure"
+}
+
+rule "always" {
+	categories "social"
+}
+
+permit "evening" {
+	length mins=30
+	categories "social"
+}
+
+permit "pointless" {
+	length mins=30
+	categories "videos"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	assert_eq!(
+		lint(&config),
+		[
+			"domains \"twitter.com\" in category \"social\" is listed more than once",
+			"category \"unused\" is not used by any rule or permit",
+			"rule \"never\" is never active",
+			"permit \"pointless\" is never available while a rule blocks its categories",
+		]
+	);
+}
+
+#[test]
+fn unused_umbrella_category() {
+	let text = r#"
+category "social" {
+	domains "twitter.com"
+}
+
+category "everything" {
+	includes "social"
+}
+
+rule "always" {
+	categories "social"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	assert_eq!(lint(&config), ["category \"everything\" is not used by any rule or permit"]);
+}
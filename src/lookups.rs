@@ -1,4 +1,4 @@
-use crate::config::Config;
+use crate::config::{Config, PathPrefix, QueryParam};
 use crate::{config, filters};
 use fixedbitset::FixedBitSet;
 use regex::RegexSet;
@@ -8,14 +8,26 @@ use url::Url;
 pub struct Lookups<'a> {
 	pub config: &'a Config,
 	// TODO: Precompute basic masks.
-	pub domain: HashMap<&'a str, Vec<usize>>,
-	pub subreddit: HashMap<&'a str, Vec<usize>>,
-	pub github: HashMap<&'a str, Vec<usize>>,
+	pub url: UrlIndex<'a>,
+	pub url_except: UrlIndex<'a>,
 	pub process: HashMap<&'a str, Vec<usize>>,
 	pub category: Table<'a, &'a config::Category>,
 	pub permit: Table<'a, &'a config::Permit>,
-	pub regex_category: Vec<usize>,
+}
+
+pub struct UrlIndex<'a> {
+	pub category_count: usize,
+	pub domain: HashMap<&'a str, Vec<usize>>,
+	pub domain_suffix: HashMap<&'a str, Vec<usize>>,
+	pub domain_wildcard: HashMap<&'a str, Vec<usize>>,
+	/// Keys matched by each of the site extractors, in the same order as the extractors.
+	pub site: Vec<HashMap<&'a str, Vec<usize>>>,
+	pub path_prefix: HashMap<Option<&'a str>, Vec<(&'a PathPrefix, Vec<usize>)>>,
+	pub query_param: HashMap<&'a str, Vec<(&'a QueryParam, Vec<usize>)>>,
+	pub regex_category: Vec<Vec<usize>>,
 	pub regex_set: RegexSet,
+	pub search_keyword_category: Vec<Vec<usize>>,
+	pub search_keyword_set: RegexSet,
 }
 
 pub struct Table<'a, T> {
@@ -26,71 +38,201 @@ pub struct Table<'a, T> {
 
 impl<'a> Lookups<'a> {
 	pub fn new(config: &'a Config) -> Self {
-		let mut domain: HashMap<&str, Vec<usize>> = HashMap::new();
-		let mut subreddit: HashMap<&str, Vec<usize>> = HashMap::new();
-		let mut github: HashMap<&str, Vec<usize>> = HashMap::new();
 		let mut process: HashMap<&str, Vec<usize>> = HashMap::new();
 		let mut category = Table::new();
 		let mut permit = Table::new();
+		for cat in &config.categories {
+			category.insert(&cat.name, cat);
+		}
+		let including = including_categories(&category);
+		let url = UrlIndex::new(config, &including, |cat| Some(&cat.matchers));
+		let url_except = UrlIndex::new(config, &including, |cat| cat.except.as_ref());
+		for (cat_index, cat) in config.categories.iter().enumerate() {
+			for proc in cat.processes.iter().flatten() {
+				process.entry(proc).or_default().extend(&including[cat_index]);
+			}
+		}
+		for per in &config.permits {
+			permit.insert(&per.name, per);
+		}
+		Lookups { config, url, url_except, process, category, permit }
+	}
+
+	/// Computes the categories matching the URL. Exceptions take precedence over regular matchers, and remove the URL
+	/// from the category that defines them and all categories that include it. URLs of pages showing other pages, like
+	/// web archives, are matched both as they are and after unwrapping them.
+	pub fn url_to_mask(&self, url: &Url) -> FixedBitSet {
+		let mut mask = self.direct_url_to_mask(url);
+		// Each unwrapping makes the URL shorter, so nested wrappers always end.
+		let mut wrapper = filters::unwrap_url(url);
+		while let Some(url) = wrapper {
+			mask.union_with(&self.direct_url_to_mask(&url));
+			wrapper = filters::unwrap_url(&url);
+		}
+		mask
+	}
+
+	fn direct_url_to_mask(&self, url: &Url) -> FixedBitSet {
+		let mut mask = self.url.url_to_mask(url);
+		mask.difference_with(&self.url_except.url_to_mask(url));
+		mask
+	}
+
+	// TODO: Do this more efficiently?
+	pub fn process_to_mask(&self, process: &str) -> FixedBitSet {
+		let mut mask = FixedBitSet::with_capacity(self.category.len());
+		if let Some(process) = self.process.get(process) {
+			mask.extend(process.iter().copied());
+		}
+		mask
+	}
+}
+
+impl<'a> UrlIndex<'a> {
+	fn new(
+		config: &'a Config,
+		including: &[Vec<usize>],
+		matchers: impl Fn(&'a config::Category) -> Option<&'a config::Matchers>,
+	) -> Self {
+		let mut domain: HashMap<&str, Vec<usize>> = HashMap::new();
+		let mut domain_suffix: HashMap<&str, Vec<usize>> = HashMap::new();
+		let mut domain_wildcard: HashMap<&str, Vec<usize>> = HashMap::new();
+		let mut site: Vec<HashMap<&str, Vec<usize>>> = vec![HashMap::new(); filters::EXTRACTORS.len()];
+		let mut path_prefix: HashMap<Option<&str>, Vec<(&PathPrefix, Vec<usize>)>> = HashMap::new();
+		let mut query_param: HashMap<&str, Vec<(&QueryParam, Vec<usize>)>> = HashMap::new();
 		let mut regex_category = Vec::new();
 		let mut regex_set_vec = Vec::new();
-		for cat in &config.categories {
-			let cat_index = category.insert(&cat.name, cat);
-			for dom in cat.domains.iter().flatten() {
-				domain.entry(dom).or_default().push(cat_index);
+		let mut search_keyword_category = Vec::new();
+		let mut search_keyword_set_vec = Vec::new();
+		for (cat_index, cat) in config.categories.iter().enumerate() {
+			let cat_indices = &including[cat_index];
+			let matchers = match matchers(cat) {
+				Some(matchers) => matchers,
+				None => continue,
+			};
+			let files = matchers.domains_files.0.iter().flat_map(|file| &file.domains);
+			for dom in matchers.domains.iter().flatten().chain(files) {
+				if let Some(parent) = dom.strip_prefix("*.") {
+					domain_wildcard.entry(parent).or_default().extend(cat_indices);
+				} else if let Some(parent) = dom.strip_prefix('.') {
+					domain_suffix.entry(parent).or_default().extend(cat_indices);
+				} else {
+					domain.entry(dom).or_default().extend(cat_indices);
+				}
+			}
+			for (site, keys) in site.iter_mut().zip(matchers.sites.iter()) {
+				for key in keys {
+					site.entry(key).or_default().extend(cat_indices);
+				}
 			}
-			for sub in cat.subreddits.iter().flatten() {
-				subreddit.entry(sub).or_default().push(cat_index);
+			for pre in matchers.path_prefixes.iter().flatten() {
+				path_prefix.entry(pre.domain.as_deref()).or_default().push((pre, cat_indices.clone()));
 			}
-			for git in cat.githubs.iter().flatten() {
-				github.entry(git).or_default().push(cat_index);
+			for par in matchers.query_params.iter().flatten() {
+				query_param.entry(&par.name).or_default().push((par, cat_indices.clone()));
 			}
-			for reg in cat.regexes.iter().flatten() {
-				regex_category.push(cat_index);
+			for reg in matchers.regexes.iter().flatten() {
+				regex_category.push(cat_indices.clone());
 				regex_set_vec.push(reg);
 			}
-			for proc in cat.processes.iter().flatten() {
-				process.entry(proc).or_default().push(cat_index);
+			for key in matchers.search_keywords.iter().flatten() {
+				search_keyword_category.push(cat_indices.clone());
+				search_keyword_set_vec.push(format!(r"(?i)(^|\W){}($|\W)", regex::escape(key)));
 			}
 		}
-		for per in &config.permits {
-			permit.insert(&per.name, per);
-		}
 		let regex_set = RegexSet::new(regex_set_vec).unwrap();
-		Lookups { config, domain, subreddit, github, process, category, permit, regex_category, regex_set }
+		let search_keyword_set = RegexSet::new(search_keyword_set_vec).unwrap();
+		UrlIndex {
+			category_count: config.categories.len(),
+			domain,
+			domain_suffix,
+			domain_wildcard,
+			site,
+			path_prefix,
+			query_param,
+			regex_category,
+			regex_set,
+			search_keyword_category,
+			search_keyword_set,
+		}
 	}
 
-	pub fn url_to_mask(&self, url: &Url) -> FixedBitSet {
-		let mut mask = FixedBitSet::with_capacity(self.category.len());
+	fn url_to_mask(&self, url: &Url) -> FixedBitSet {
+		let mut mask = FixedBitSet::with_capacity(self.category_count);
 		if let Some(domain) = url.domain() {
-			if let Some(categories) = self.domain.get(domain) {
-				mask.extend(categories.iter().copied());
+			let domain = domain.strip_suffix('.').unwrap_or(domain);
+			let mut extend = |categories: Option<&Vec<usize>>| mask.extend(categories.into_iter().flatten().copied());
+			extend(self.domain.get(domain));
+			extend(self.domain_suffix.get(domain));
+			// Checking each parent domain keeps the lookup linear in the number of labels, not the number of patterns.
+			for (dot, _) in domain.match_indices('.') {
+				let parent = &domain[dot + 1..];
+				extend(self.domain_suffix.get(parent));
+				extend(self.domain_wildcard.get(parent));
 			}
 		}
-		if let Some(subreddit) = filters::extract_subreddit(url) {
-			if let Some(categories) = self.subreddit.get(subreddit.as_str()) {
-				mask.extend(categories.iter().copied());
+		for (extractor, site) in filters::EXTRACTORS.iter().zip(&self.site) {
+			if let Some(key) = extractor.extract(url) {
+				if let Some(categories) = site.get(key.as_str()) {
+					mask.extend(categories.iter().copied());
+				}
 			}
 		}
-		if let Some(github) = filters::extract_github(url) {
-			if let Some(categories) = self.github.get(github.as_str()) {
-				mask.extend(categories.iter().copied());
+		let domain = url.domain().map(|domain| domain.strip_suffix('.').unwrap_or(domain));
+		for prefixes in [self.path_prefix.get(&domain), self.path_prefix.get(&None)].iter().flatten() {
+			for (prefix, categories) in prefixes.iter() {
+				if filters::path_has_prefix(url.path(), &prefix.prefix) {
+					mask.extend(categories.iter().copied());
+				}
 			}
 		}
+		for (name, value) in url.query_pairs() {
+			for (param, categories) in self.query_param.get(name.as_ref()).into_iter().flatten() {
+				let domain_matches = param.domain.is_none() || param.domain.as_deref() == domain;
+				let value_matches = param.value.is_none() || param.value.as_deref() == Some(value.as_ref());
+				if domain_matches && value_matches {
+					mask.extend(categories.iter().copied());
+				}
+			}
+		}
+		if let Some(query) = filters::extract_search_query(url) {
+			mask.extend(
+				self.search_keyword_set
+					.matches(&query)
+					.into_iter()
+					.flat_map(|keyword_index| self.search_keyword_category[keyword_index].iter().copied()),
+			);
+		}
 		mask.extend(
-			self.regex_set.matches(url.as_str()).into_iter().map(|regex_index| self.regex_category[regex_index]),
+			self.regex_set
+				.matches(url.as_str())
+				.into_iter()
+				.flat_map(|regex_index| self.regex_category[regex_index].iter().copied()),
 		);
 		mask
 	}
+}
 
-	// TODO: Do this more efficiently?
-	pub fn process_to_mask(&self, process: &str) -> FixedBitSet {
-		let mut mask = FixedBitSet::with_capacity(self.category.len());
-		if let Some(process) = self.process.get(process) {
-			mask.extend(process.iter().copied());
+/// For each category, computes the list of categories that include it directly or indirectly, including itself.
+fn including_categories(category: &Table<&config::Category>) -> Vec<Vec<usize>> {
+	let mut included_by = vec![Vec::new(); category.len()];
+	for (cat_index, cat) in category.details.iter().enumerate() {
+		for include in cat.includes.iter().flatten() {
+			included_by[category.id[include.as_str()]].push(cat_index);
 		}
-		mask
 	}
+	(0..category.len())
+		.map(|cat_index| {
+			let mut visited = FixedBitSet::with_capacity(category.len());
+			let mut stack = vec![cat_index];
+			while let Some(current) = stack.pop() {
+				if !visited.put(current) {
+					stack.extend(&included_by[current]);
+				}
+			}
+			visited.ones().collect()
+		})
+		.collect()
 }
 
 impl<'a, T> Table<'a, T> {
@@ -110,3 +252,182 @@ impl<'a, T> Table<'a, T> {
 		self.id.len()
 	}
 }
+
+#[test]
+fn includes() {
+	let text = r#"
+category "leisure" {
+	includes "video" "social"
+}
+
+category "video" {
+	domains "www.youtube.com"
+}
+
+category "social" {
+	domains "twitter.com"
+	includes "video"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let lookups = Lookups::new(&config);
+	let mask = lookups.url_to_mask(&"https://www.youtube.com/".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [0, 1, 2]);
+	let mask = lookups.url_to_mask(&"https://twitter.com/".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [0, 2]);
+}
+
+#[test]
+fn exceptions() {
+	let text = r#"
+category "leisure" {
+	includes "reddit"
+	githubs "unneon/icie"
+}
+
+category "reddit" {
+	domains "www.reddit.com"
+	except {
+		subreddits "rust"
+	}
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let lookups = Lookups::new(&config);
+	let mask = lookups.url_to_mask(&"https://www.reddit.com/r/funny/".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [0, 1]);
+	let mask = lookups.url_to_mask(&"https://www.reddit.com/r/rust/".parse().unwrap());
+	assert_eq!(mask.ones().count(), 0);
+}
+
+#[test]
+fn domain_patterns() {
+	let text = r#"
+category "exact" {
+	domains "YouTube.com."
+}
+
+category "suffix" {
+	domains ".youtube.com"
+}
+
+category "wildcard" {
+	domains "*.youtube.com"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let lookups = Lookups::new(&config);
+	let mask = lookups.url_to_mask(&"https://youtube.com/".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [0, 1]);
+	let mask = lookups.url_to_mask(&"https://music.youtube.com./".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [1, 2]);
+	let mask = lookups.url_to_mask(&"https://notyoutube.com/".parse().unwrap());
+	assert_eq!(mask.ones().count(), 0);
+}
+
+#[test]
+fn path_and_query() {
+	let text = r#"
+category "shorts" {
+	path-prefixes "www.youtube.com/shorts"
+}
+
+category "playlist" {
+	query-params "www.youtube.com?list=PL123"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let lookups = Lookups::new(&config);
+	let mask = lookups.url_to_mask(&"https://www.youtube.com/shorts/abc".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [0]);
+	let mask = lookups.url_to_mask(&"https://www.youtube.com/watch?v=abc&list=PL123".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [1]);
+	let mask = lookups.url_to_mask(&"https://m.youtube.com/shorts/abc?list=PL123".parse().unwrap());
+	assert_eq!(mask.ones().count(), 0);
+}
+
+#[test]
+fn search_keywords() {
+	let text = r#"
+category "memes" {
+	search-keywords "memes" "c++"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let lookups = Lookups::new(&config);
+	let mask = lookups.url_to_mask(&"https://duckduckgo.com/?q=Cat+MEMES".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [0]);
+	let mask = lookups.url_to_mask(&"https://www.google.com/search?q=c%2B%2B+templates".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [0]);
+	let mask = lookups.url_to_mask(&"https://www.google.com/search?q=mememaker".parse().unwrap());
+	assert_eq!(mask.ones().count(), 0);
+}
+
+#[test]
+fn sites() {
+	let text = r#"
+category "streams" {
+	twitch-channels "gamesdonequick"
+	youtube-channels "@veritasium"
+}
+
+category "questions" {
+	stackexchange-sites "math"
+	except {
+		stackexchange-sites "math"
+	}
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let lookups = Lookups::new(&config);
+	let mask = lookups.url_to_mask(&"https://www.twitch.tv/GamesDoneQuick".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [0]);
+	let mask = lookups.url_to_mask(&"https://www.youtube.com/@veritasium".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [0]);
+	let mask = lookups.url_to_mask(&"https://math.stackexchange.com/questions/1".parse().unwrap());
+	assert_eq!(mask.ones().count(), 0);
+}
+
+#[test]
+fn mixed_case_site_keys() {
+	let text = r#"
+category "feeds" {
+	subreddits "AskHistorians"
+	youtube-channels "@Veritasium"
+	twitch-channels "GamesDoneQuick"
+	twitter-accounts "NASA"
+	mastodon-accounts "Gargron@Mastodon.Social"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let lookups = Lookups::new(&config);
+	let urls = [
+		"https://www.reddit.com/r/askhistorians/",
+		"https://www.youtube.com/@veritasium",
+		"https://www.twitch.tv/gamesdonequick",
+		"https://x.com/nasa",
+		"https://mastodon.social/@Gargron",
+	];
+	for url in urls.iter() {
+		assert_eq!(lookups.url_to_mask(&url.parse().unwrap()).ones().collect::<Vec<_>>(), [0], "{}", url);
+	}
+}
+
+#[test]
+fn wrapped_urls() {
+	let text = r#"
+category "reddit" {
+	subreddits "funny"
+}
+
+category "archive" {
+	domains "web.archive.org"
+}
+"#;
+	let config = Config::parse(text).unwrap();
+	let lookups = Lookups::new(&config);
+	let mask = lookups.url_to_mask(&"view-source:https://www.reddit.com/r/funny/".parse().unwrap());
+	assert_eq!(mask.ones().collect::<Vec<_>>(), [0]);
+	let url = "view-source:https://web.archive.org/web/2021/https://www.reddit.com/r/funny/".parse().unwrap();
+	assert_eq!(lookups.url_to_mask(&url).ones().collect::<Vec<_>>(), [0, 1]);
+}
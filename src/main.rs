@@ -1,13 +1,18 @@
 mod cli;
 mod config;
 mod dbus;
+mod export;
 mod filters;
+mod import;
+mod lint;
 mod logger;
 mod lookups;
+mod paths;
 mod permits;
 mod processes;
 mod rules;
 mod tabs;
+mod watcher;
 mod webext;
 
 use crate::config::{Config, ConfigError};
@@ -25,12 +30,13 @@ use url::Url;
 
 #[derive(Debug)]
 pub enum Event {
-	PermitRequest { name: String, err_tx: mpsc::SyncSender<PermitResult> },
+	PermitRequest { name: String, length: Option<Duration>, err_tx: mpsc::SyncSender<PermitResult> },
 	PermitEnd { name: String, err_tx: mpsc::SyncSender<PermitResult> },
 	TabUpdate { tab: TabId, url: Url },
 	TabDelete { tab: TabId },
 	TabDeleteAll { pid: u32 },
 	ServiceReload { err_tx: mpsc::SyncSender<Result<(), ConfigError>> },
+	ConfigChange,
 }
 
 struct SaveState {
@@ -42,21 +48,53 @@ struct SaveState {
 fn main() {
 	logger::init().unwrap();
 	webext::proxy::check_and_run();
-	if std::env::args().nth(1).as_deref() == Some("daemon") {
+	let args = match paths::init(std::env::args().collect()) {
+		Ok(args) => args,
+		Err(err) => {
+			println!("\x1B[1;31merror:\x1B[0m {}", err);
+			std::process::exit(1);
+		}
+	};
+	if args.get(1).map(String::as_str) == Some("daemon") {
 		run_daemon_outer()
 	} else {
-		cli::run();
+		cli::run(&args);
 	}
 }
 
 fn run_daemon_outer() {
-	let config = Config::load().unwrap();
+	let config = match Config::load() {
+		Ok(config) => config,
+		Err(err) => {
+			log::error!("{}", err);
+			std::process::exit(1);
+		}
+	};
+	if let Err(err) = std::fs::create_dir_all(paths::state_dir()) {
+		log::error!("failed to create state directory {:?} ({})", paths::state_dir(), err);
+	}
 	let event_queue = mpsc::channel();
+	let watcher = match watcher::Watcher::new(event_queue.0.clone()) {
+		Ok(watcher) => Some(watcher),
+		Err(err) => {
+			log::warn!("failed to watch the config for changes ({})", err);
+			None
+		}
+	};
 	let dbus = DBus::new(event_queue.0);
 	dbus.refresh();
 
-	let mut save_state = SaveState { config, tabs: Default::default(), permits: Default::default() };
+	let permits_path = paths::state_dir().join(permits::STATE_FILE_NAME);
+	let permits = PermitSaveState::load(&permits_path, &Local::now());
+	// Store right away, so that restarting again doesn't also restart the cooldown after restart.
+	if let Err(err) = permits.store(&permits_path) {
+		log::error!("failed to store permit state in {:?} ({})", permits_path, err);
+	}
+	let mut save_state = SaveState { config, tabs: Default::default(), permits };
 	loop {
+		if let Some(watcher) = &watcher {
+			watcher.watch(&save_state.config);
+		}
 		save_state = run_daemon(save_state, &dbus, &event_queue.1);
 	}
 }
@@ -83,8 +121,9 @@ fn run_daemon(save_state: SaveState, dbus: &DBus, event_queue: &mpsc::Receiver<E
 
 		if let Some(event) = event {
 			match event {
-				Event::PermitRequest { name, err_tx } => {
-					err_tx.send(permits.activate(&name, &now)).unwrap();
+				Event::PermitRequest { name, length, err_tx } => {
+					err_tx.send(permits.activate(&name, length, &now)).unwrap();
+					store_permits(&permits);
 					permits.reload(&now);
 					tabs.rescan(rules.blocked(), permits.unblocked(), dbus, &now);
 					processes.rescan(rules.blocked(), permits.unblocked(), &now);
@@ -92,6 +131,7 @@ fn run_daemon(save_state: SaveState, dbus: &DBus, event_queue: &mpsc::Receiver<E
 				}
 				Event::PermitEnd { name, err_tx } => {
 					err_tx.send(permits.deactivate(&name)).unwrap();
+					store_permits(&permits);
 					permits.reload(&now);
 					tabs.rescan(rules.blocked(), permits.unblocked(), dbus, &now);
 					processes.rescan(rules.blocked(), permits.unblocked(), &now);
@@ -111,7 +151,21 @@ fn run_daemon(save_state: SaveState, dbus: &DBus, event_queue: &mpsc::Receiver<E
 							permits: permits.save_state(),
 						};
 					}
-					Err(err) => err_tx.send(Err(err)).unwrap(),
+					Err(err) => {
+						log::error!("{}", err);
+						err_tx.send(Err(err)).unwrap();
+					}
+				},
+				Event::ConfigChange => match Config::load() {
+					Ok(new_config) => {
+						log::info!("config changed, reloading");
+						return SaveState {
+							config: new_config,
+							tabs: tabs.save_state(),
+							permits: permits.save_state(),
+						};
+					}
+					Err(err) => log::error!("{}, keeping the previous config", err),
 				},
 			}
 		} else {
@@ -124,6 +178,13 @@ fn run_daemon(save_state: SaveState, dbus: &DBus, event_queue: &mpsc::Receiver<E
 	}
 }
 
+fn store_permits(permits: &PermitManager) {
+	let path = paths::state_dir().join(permits::STATE_FILE_NAME);
+	if let Err(err) = permits.store(&path) {
+		log::error!("failed to store permit state in {:?} ({})", path, err);
+	}
+}
+
 fn recv_maybe<T>(rx: &mpsc::Receiver<T>, timeout: Option<Duration>) -> Result<Option<T>, mpsc::RecvError> {
 	match timeout {
 		Some(timeout) => match rx.recv_timeout(timeout) {
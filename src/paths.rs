@@ -0,0 +1,66 @@
+use crate::config::CONFIG_FILE_NAME;
+use once_cell::sync::OnceCell;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+static CONFIG_FILE: OnceCell<PathBuf> = OnceCell::new();
+static STATE_DIR: OnceCell<PathBuf> = OnceCell::new();
+static BUS_NAME: OnceCell<String> = OnceCell::new();
+
+pub const DEFAULT_BUS_NAME: &str = "solar.unneon.Vaxtify";
+
+/// Removes `--config PATH`, `--state-dir PATH` and `--bus-name NAME` flags from the arguments and remembers their
+/// values, so that a test instance can run next to the default one. The `VAXTIFY_CONFIG`, `VAXTIFY_STATE_DIR` and
+/// `VAXTIFY_BUS_NAME` environment variables can be used instead.
+pub fn init(args: Vec<String>) -> Result<Vec<String>, &'static str> {
+	let mut rest = Vec::new();
+	let mut args = args.into_iter();
+	while let Some(arg) = args.next() {
+		if !matches!(arg.as_str(), "--config" | "--state-dir" | "--bus-name") {
+			rest.push(arg);
+			continue;
+		}
+		let value = args.next().ok_or("expected a value after --config, --state-dir and --bus-name")?;
+		let is_new = match arg.as_str() {
+			"--config" => CONFIG_FILE.set(PathBuf::from(value)).is_ok(),
+			"--state-dir" => STATE_DIR.set(PathBuf::from(value)).is_ok(),
+			_ => BUS_NAME.set(value).is_ok(),
+		};
+		if !is_new {
+			return Err("--config, --state-dir and --bus-name can only be given once");
+		}
+	}
+	Ok(rest)
+}
+
+pub fn config_file() -> &'static PathBuf {
+	CONFIG_FILE.get_or_init(|| match env_path("VAXTIFY_CONFIG") {
+		Some(path) => path,
+		None => dirs::config_dir().unwrap().join(CONFIG_FILE_NAME),
+	})
+}
+
+/// Directory for data the daemon keeps between runs, following the XDG base directory specification.
+pub fn state_dir() -> &'static PathBuf {
+	STATE_DIR.get_or_init(|| {
+		if let Some(path) = env_path("VAXTIFY_STATE_DIR") {
+			return path;
+		}
+		match env_path("XDG_STATE_HOME").filter(|path| path.is_absolute()) {
+			Some(state_home) => state_home.join("vaxtify"),
+			None => dirs::home_dir().unwrap().join(".local/state/vaxtify"),
+		}
+	})
+}
+
+/// D-Bus name the daemon owns, which the web extension and the command line client send their calls to.
+pub fn bus_name() -> &'static str {
+	BUS_NAME.get_or_init(|| match std::env::var("VAXTIFY_BUS_NAME") {
+		Ok(name) if !name.is_empty() => name,
+		_ => DEFAULT_BUS_NAME.to_owned(),
+	})
+}
+
+fn env_path(name: &str) -> Option<PathBuf> {
+	std::env::var_os(name).filter(|value| !value.is_empty()).map(OsString::into)
+}
@@ -1,10 +1,15 @@
 use crate::config;
 use crate::lookups::Lookups;
-use chrono::{DateTime, Local, NaiveTime};
+use chrono::{DateTime, Local};
 use fixedbitset::FixedBitSet;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::Path;
 use std::time::Duration;
 
+pub const STATE_FILE_NAME: &str = "permits.json";
+
 #[derive(Debug, thiserror::Error)]
 pub enum PermitError {
 	#[error("permit {name:?} does not exist")]
@@ -19,8 +24,8 @@ pub enum PermitError {
 	DurationNotSpecified,
 	#[error("cooldown is not finished ({left:?} left)")]
 	CooldownNotFinished { left: Duration },
-	#[error("permit is not available (only since {since} until {until})")]
-	AvailableBadTime { since: NaiveTime, until: NaiveTime },
+	#[error("permit is not available (only {available})")]
+	AvailableBadTime { available: String },
 	#[error("cooldown after restart is not finished ({left:?} left)")]
 	CooldownAfterRestart { left: Duration },
 }
@@ -29,14 +34,19 @@ pub struct PermitManager<'a> {
 	lookups: &'a Lookups<'a>,
 	unblocked: FixedBitSet,
 	state: Vec<PermitState>,
+	untrusted_since: Option<DateTime<Local>>,
 }
 
-#[derive(Default)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct PermitSaveState {
+	#[serde(rename = "permits")]
 	state: HashMap<String, PermitState>,
+	/// When the daemon started without trustworthy saved state, which means permits could have been used recently.
+	#[serde(default)]
+	untrusted_since: Option<DateTime<Local>>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 struct PermitState {
 	expires: Option<DateTime<Local>>,
 	last_active: Option<DateTime<Local>>,
@@ -61,21 +71,24 @@ impl<'a> PermitManager<'a> {
 				state[*permit_index] = permit_state;
 			}
 		}
-		PermitManager { lookups, unblocked, state }
+		PermitManager { lookups, unblocked, state, untrusted_since: save_state.untrusted_since }
 	}
 
 	pub fn unblocked(&self) -> &FixedBitSet {
 		&self.unblocked
 	}
 
-	pub fn activate(&mut self, name: &str, now: &DateTime<Local>) -> PermitResult {
+	/// Starts the permit for the requested length, or its default length if none was requested.
+	pub fn activate(&mut self, name: &str, length: Option<Duration>, now: &DateTime<Local>) -> PermitResult {
 		let id = self.get_permit(name)?;
 		let details = self.lookups.permit.details[id];
+		let length = check_length(length, details)?;
+		check_cooldown_after_restart(now, &mut self.untrusted_since, self.lookups.config)?;
 		let state = &mut self.state[id];
 		check_cooldown(now, state, details)?;
 		check_available(now, details)?;
 		state.last_active = Some(*now);
-		state.expires = Some(*now + chrono::Duration::from_std(details.length.into()).unwrap());
+		state.expires = Some(*now + chrono::Duration::from_std(length).unwrap());
 		Ok(())
 	}
 
@@ -109,9 +122,15 @@ impl<'a> PermitManager<'a> {
 	}
 
 	pub fn save_state(self) -> PermitSaveState {
-		PermitSaveState {
-			state: self.lookups.permit.name.iter().copied().map(str::to_owned).zip(self.state.into_iter()).collect(),
-		}
+		let state = self.lookups.permit.name.iter().copied().map(str::to_owned).zip(self.state).collect();
+		PermitSaveState { state, untrusted_since: self.untrusted_since }
+	}
+
+	/// Writes the state of all permits to the given file, so that restarting the daemon doesn't reset cooldowns.
+	pub fn store(&self, path: &Path) -> std::io::Result<()> {
+		let names = self.lookups.permit.name.iter().copied().map(str::to_owned);
+		let state = names.zip(self.state.iter().cloned()).collect();
+		PermitSaveState { state, untrusted_since: self.untrusted_since }.store(path)
 	}
 
 	fn get_permit(&self, name: &str) -> Result<usize, PermitError> {
@@ -124,6 +143,62 @@ impl<'a> PermitManager<'a> {
 	}
 }
 
+impl PermitSaveState {
+	/// Reads the state stored by a previous run of the daemon. If it's missing or corrupt, starts from scratch and
+	/// marks the state as untrusted since now, so that the cooldown after restart applies.
+	pub fn load(path: &Path, now: &DateTime<Local>) -> PermitSaveState {
+		let untrusted = PermitSaveState { state: HashMap::new(), untrusted_since: Some(*now) };
+		let text = match std::fs::read_to_string(path) {
+			Ok(text) => text,
+			Err(e) if e.kind() == ErrorKind::NotFound => return untrusted,
+			Err(e) => {
+				log::error!("failed to read permit state from {:?} ({})", path, e);
+				return untrusted;
+			}
+		};
+		match serde_json::from_str(&text) {
+			Ok(save_state) => save_state,
+			Err(e) => {
+				log::error!("ignoring corrupt permit state in {:?} ({})", path, e);
+				untrusted
+			}
+		}
+	}
+
+	/// Replaces the file atomically, so that a crash while writing can't corrupt it.
+	pub fn store(&self, path: &Path) -> std::io::Result<()> {
+		let temporary = path.with_extension("json.tmp");
+		std::fs::write(&temporary, serde_json::to_string_pretty(self)?)?;
+		std::fs::rename(&temporary, path)
+	}
+}
+
+fn check_length(length: Option<Duration>, details: &config::Permit) -> Result<Duration, PermitError> {
+	let maximum = details.max_length.or(details.length).map(Duration::from);
+	match (length, maximum) {
+		(Some(got), Some(maximum)) if got > maximum => {
+			Err(PermitError::DurationTooLong { got, permit: details.name.clone(), maximum })
+		}
+		(Some(got), _) => Ok(got),
+		(None, _) => details.length.map(Duration::from).ok_or(PermitError::DurationNotSpecified),
+	}
+}
+
+fn check_cooldown_after_restart(
+	now: &DateTime<Local>,
+	untrusted_since: &mut Option<DateTime<Local>>,
+	config: &config::Config,
+) -> PermitResult {
+	if let (Some(since), Some(cooldown)) = (*untrusted_since, config.permit_cooldown_after_restart) {
+		let elapsed = (*now - since).to_std().unwrap_or_default();
+		if elapsed < Duration::from(cooldown) {
+			return Err(PermitError::CooldownAfterRestart { left: whole_seconds(Duration::from(cooldown) - elapsed) });
+		}
+	}
+	*untrusted_since = None;
+	Ok(())
+}
+
 fn check_cooldown(now: &DateTime<Local>, state: &PermitState, details: &config::Permit) -> PermitResult {
 	match (state.last_active, details.cooldown) {
 		(Some(last_active), Some(cooldown))
@@ -146,13 +221,16 @@ fn check_available(now: &DateTime<Local>, details: &config::Permit) -> PermitRes
 	if details.is_available(now) {
 		Ok(())
 	} else {
-		Err(PermitError::AvailableBadTime {
-			since: details.available.unwrap().since.into(),
-			until: details.available.unwrap().until.into(),
-		})
+		let available = details.available.iter().map(ToString::to_string).collect::<Vec<_>>().join(" or ");
+		Err(PermitError::AvailableBadTime { available })
 	}
 }
 
+/// Rounds up the time left, so that errors don't show fractions of seconds.
+fn whole_seconds(duration: Duration) -> Duration {
+	Duration::from_secs(duration.as_secs() + u64::from(duration.subsec_nanos() > 0))
+}
+
 fn check_active(state: &PermitState) -> PermitResult {
 	if state.expires.is_some() {
 		Ok(())
@@ -160,3 +238,86 @@ fn check_active(state: &PermitState) -> PermitResult {
 		Err(PermitError::PermitIsNotActive)
 	}
 }
+
+#[test]
+fn save_state_file() {
+	use chrono::TimeZone;
+	let dir = std::env::temp_dir().join(format!("vaxtify-permits-test-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join(STATE_FILE_NAME);
+	let last_active = Local.ymd(2026, 1, 5).and_hms(20, 0, 0);
+	let missing = PermitSaveState::load(&path, &last_active);
+	assert!(missing.state.is_empty());
+	assert_eq!(missing.untrusted_since, Some(last_active));
+	let state =
+		PermitState { expires: Some(last_active + chrono::Duration::minutes(30)), last_active: Some(last_active) };
+	let state_map = vec![("memes".to_owned(), state.clone())].into_iter().collect();
+	PermitSaveState { state: state_map, untrusted_since: None }.store(&path).unwrap();
+	let loaded = PermitSaveState::load(&path, &last_active);
+	assert_eq!(loaded.state["memes"], state);
+	assert_eq!(loaded.untrusted_since, None);
+	std::fs::write(&path, "{\"permits\": ").unwrap();
+	let corrupt = PermitSaveState::load(&path, &last_active);
+	std::fs::remove_dir_all(&dir).unwrap();
+	assert!(corrupt.state.is_empty());
+	assert_eq!(corrupt.untrusted_since, Some(last_active));
+}
+
+#[test]
+fn cooldown_after_restart() {
+	use chrono::TimeZone;
+	let text = r#"
+permit-cooldown-after-restart mins=10
+
+category "memes" {
+	domains "9gag.com"
+}
+
+permit "memes" {
+	length mins=15
+	categories "memes"
+}
+"#;
+	let config = config::Config::parse(text).unwrap();
+	let lookups = Lookups::new(&config);
+	let started = Local.ymd(2026, 1, 5).and_hms(20, 0, 0);
+	let save_state = PermitSaveState { state: HashMap::new(), untrusted_since: Some(started) };
+	let mut permits = PermitManager::new(&lookups, save_state);
+	match permits.activate("memes", None, &(started + chrono::Duration::minutes(4))) {
+		Err(PermitError::CooldownAfterRestart { left }) => assert_eq!(left, Duration::from_secs(6 * 60)),
+		result => panic!("{:?}", result),
+	}
+	assert!(permits.activate("memes", None, &(started + chrono::Duration::minutes(10))).is_ok());
+	assert_eq!(permits.save_state().untrusted_since, None);
+}
+
+#[test]
+fn lengths() {
+	use chrono::TimeZone;
+	let text = r#"
+category "memes" {
+	domains "9gag.com"
+}
+
+permit "default" {
+	length mins=15
+	categories "memes"
+}
+
+permit "chosen" {
+	max-length mins=30
+	categories "memes"
+}
+"#;
+	let config = config::Config::parse(text).unwrap();
+	let lookups = Lookups::new(&config);
+	let mut permits = PermitManager::new(&lookups, PermitSaveState::default());
+	let now = Local.ymd(2026, 1, 5).and_hms(20, 0, 0);
+	let minutes = |minutes: u64| Some(Duration::from_secs(minutes * 60));
+	assert!(matches!(permits.activate("default", minutes(20), &now), Err(PermitError::DurationTooLong { .. })));
+	assert!(permits.activate("default", minutes(5), &now).is_ok());
+	assert_eq!(permits.when_reload(), Some(now + chrono::Duration::minutes(5)));
+	assert!(matches!(permits.activate("chosen", None, &now), Err(PermitError::DurationNotSpecified)));
+	assert!(matches!(permits.activate("chosen", minutes(31), &now), Err(PermitError::DurationTooLong { .. })));
+	assert!(permits.activate("chosen", minutes(30), &now).is_ok());
+}
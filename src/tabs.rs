@@ -65,7 +65,7 @@ impl<'a> Tabs<'a> {
 	}
 
 	fn should_block_all(&self, now: &DateTime<Local>) -> bool {
-		self.block_all_until.map_or(false, |block_all_until| *now <= block_all_until)
+		self.block_all_until.is_some_and(|block_all_until| *now <= block_all_until)
 	}
 
 	pub fn remove(&mut self, tab: TabId) {
@@ -116,6 +116,8 @@ impl<'a> Tabs<'a> {
 	}
 }
 
+/// Checks whether a tab should be closed. Category exceptions are already removed from the mask by the lookups, so the
+/// tab is blocked if any active rule matches a remaining category and no permit matches any of them.
 fn should_block_mask(mask: &FixedBitSet, blocked: &FixedBitSet, unblocked: &FixedBitSet) -> bool {
 	mask.intersection(blocked).count() > 0 && mask.intersection(unblocked).count() == 0
 }
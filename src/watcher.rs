@@ -0,0 +1,153 @@
+use crate::config::Config;
+use crate::Event;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CString, OsStr};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_RELOAD_DELAY: Duration = Duration::from_millis(500);
+
+// Editors often save by writing a new file and renaming it over the old one, so directories are watched instead of
+// the files themselves.
+const MASK: u32 = libc::IN_CLOSE_WRITE | libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO;
+
+/// Watches the files the config was loaded from with inotify, and sends an event once they stop changing.
+pub struct Watcher {
+	fd: i32,
+	state: Arc<Mutex<State>>,
+}
+
+struct State {
+	dirs: HashMap<i32, PathBuf>,
+	files: HashSet<PathBuf>,
+	drop_in_dir: Option<PathBuf>,
+	delay: Duration,
+}
+
+impl Watcher {
+	pub fn new(tx: mpsc::Sender<Event>) -> std::io::Result<Watcher> {
+		let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+		if fd < 0 {
+			return Err(std::io::Error::last_os_error());
+		}
+		let state =
+			State { dirs: HashMap::new(), files: HashSet::new(), drop_in_dir: None, delay: DEFAULT_RELOAD_DELAY };
+		let state = Arc::new(Mutex::new(state));
+		let thread_state = state.clone();
+		std::thread::spawn(move || run(fd, &thread_state, &tx));
+		Ok(Watcher { fd, state })
+	}
+
+	/// Replaces the watched files with the ones the given config was loaded from.
+	pub fn watch(&self, config: &Config) {
+		let mut state = self.state.lock().unwrap();
+		state.files = config.files.iter().cloned().chain(config.drop_in_dir.clone()).collect();
+		state.drop_in_dir = config.drop_in_dir.clone();
+		state.delay = config.reload_delay.map_or(DEFAULT_RELOAD_DELAY, Duration::from);
+		let parents = state.files.iter().filter_map(|file| file.parent()).map(Path::to_owned).collect::<HashSet<_>>();
+		let mut dirs = HashMap::new();
+		for dir in parents.iter().chain(&config.drop_in_dir) {
+			// Watching a directory again returns the same descriptor, and the drop-in directory might not exist yet.
+			let path = CString::new(dir.as_os_str().as_bytes()).unwrap();
+			let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), MASK) };
+			if wd >= 0 {
+				dirs.insert(wd, dir.clone());
+			}
+		}
+		for wd in state.dirs.keys().filter(|wd| !dirs.contains_key(wd)) {
+			unsafe { libc::inotify_rm_watch(self.fd, *wd) };
+		}
+		state.dirs = dirs;
+	}
+}
+
+fn run(fd: i32, state: &Mutex<State>, tx: &mpsc::Sender<Event>) {
+	loop {
+		if !read_changes(fd, state) {
+			continue;
+		}
+		// Only changes to config files restart the wait, so other files in the same directories can't delay reloading.
+		let delay = state.lock().unwrap().delay;
+		let mut deadline = Instant::now() + delay;
+		while poll(fd, deadline.saturating_duration_since(Instant::now())) {
+			if read_changes(fd, state) {
+				deadline = Instant::now() + delay;
+			}
+		}
+		if tx.send(Event::ConfigChange).is_err() {
+			return;
+		}
+	}
+}
+
+/// Blocks until there are some events, and returns whether any of them touched a config file.
+fn read_changes(fd: i32, state: &Mutex<State>) -> bool {
+	let mut buffer = [0u8; 4096];
+	let len = unsafe { libc::read(fd, buffer.as_mut_ptr().cast(), buffer.len()) };
+	if len <= 0 {
+		log::error!("failed to read config file changes ({})", std::io::Error::last_os_error());
+		std::thread::sleep(Duration::from_secs(1));
+		return false;
+	}
+	let state = state.lock().unwrap();
+	let mut changed = false;
+	let mut offset = 0;
+	while offset < len as usize {
+		let event: libc::inotify_event = unsafe { std::ptr::read_unaligned(buffer[offset..].as_ptr().cast()) };
+		let name_start = offset + std::mem::size_of::<libc::inotify_event>();
+		let name = &buffer[name_start..name_start + event.len as usize];
+		let name = OsStr::from_bytes(name.split(|byte| *byte == 0).next().unwrap());
+		if let Some(dir) = state.dirs.get(&event.wd) {
+			let path = dir.join(name);
+			let is_drop_in = state.drop_in_dir.as_ref() == Some(dir) && path.extension() == Some("kdl".as_ref());
+			changed |= is_drop_in || state.files.contains(&path);
+		}
+		offset = name_start + event.len as usize;
+	}
+	changed
+}
+
+/// Waits for more events for at most the given time, and returns whether there are any.
+fn poll(fd: i32, timeout: Duration) -> bool {
+	let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+	unsafe { libc::poll(&mut pollfd, 1, timeout.as_millis().min(i32::MAX as u128) as i32) > 0 }
+}
+
+#[test]
+fn config_change() {
+	let dir = std::env::temp_dir().join(format!("vaxtify-watcher-test-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("vaxtify.kdl");
+	std::fs::write(&path, "reload-delay seconds=0\n").unwrap();
+	std::fs::write(dir.join("other.kdl"), "").unwrap();
+	let config = Config::load_file(&path).unwrap();
+	let (tx, rx) = mpsc::channel();
+	let watcher = Watcher::new(tx).unwrap();
+	watcher.watch(&config);
+	std::fs::write(dir.join("other.kdl"), "category \"social\" {\n}\n").unwrap();
+	assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+	std::fs::write(dir.join("vaxtify.kdl.tmp"), "prevent-browser-close\n").unwrap();
+	std::fs::rename(dir.join("vaxtify.kdl.tmp"), &path).unwrap();
+	let event = rx.recv_timeout(Duration::from_secs(5));
+	std::fs::remove_dir_all(&dir).unwrap();
+	assert!(matches!(event, Ok(Event::ConfigChange)));
+}
+
+#[test]
+fn stale_watches() {
+	let dir = std::env::temp_dir().join(format!("vaxtify-watcher-stale-test-{}", std::process::id()));
+	std::fs::create_dir_all(dir.join("old")).unwrap();
+	std::fs::create_dir_all(dir.join("new")).unwrap();
+	std::fs::write(dir.join("old/vaxtify.kdl"), "").unwrap();
+	std::fs::write(dir.join("new/vaxtify.kdl"), "").unwrap();
+	let (tx, _rx) = mpsc::channel();
+	let watcher = Watcher::new(tx).unwrap();
+	watcher.watch(&Config::load_file(&dir.join("old/vaxtify.kdl")).unwrap());
+	watcher.watch(&Config::load_file(&dir.join("new/vaxtify.kdl")).unwrap());
+	let dirs = watcher.state.lock().unwrap().dirs.values().cloned().collect::<Vec<_>>();
+	let expected = dir.join("new").canonicalize().unwrap();
+	std::fs::remove_dir_all(&dir).unwrap();
+	assert_eq!(dirs, [expected]);
+}
@@ -26,7 +26,7 @@ fn spawn_signals_to_commands() {
 		// TODO: Avoid creating two connections? This caused dropped return values before.
 		let pid = std::process::id();
 		let conn = LocalConnection::new_session().unwrap();
-		let proxy = conn.with_proxy("solar.unneon.Vaxtify", "/", Duration::from_millis(5000));
+		let proxy = conn.with_proxy(crate::paths::bus_name(), "/", Duration::from_millis(5000));
 		proxy
 			.match_signal(move |h: SolarUnneonVaxtifyTabClose, _: &LocalConnection, _: &Message| {
 				// TODO: Delegate PID filter to dbus instead, somehow?
@@ -70,7 +70,7 @@ fn run_events_to_calls() {
 	let conn = LocalConnection::new_session().unwrap();
 	let stdin = std::io::stdin();
 	let mut stdin = stdin.lock();
-	let proxy = conn.with_proxy("solar.unneon.Vaxtify", "/", Duration::from_millis(5000));
+	let proxy = conn.with_proxy(crate::paths::bus_name(), "/", Duration::from_millis(5000));
 	proxy.browser_register(pid).unwrap();
 	while let Ok(message) = protocol::read(&mut stdin) {
 		match deserialize_event(&message) {